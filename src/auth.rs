@@ -63,31 +63,29 @@ impl Authenticator {
             } else {
                 let status = res.status();
                 let error_body = res.text().await.unwrap_or_default();
-                if status == reqwest::StatusCode::BAD_REQUEST {
-                    if let Ok(error_json) = serde_json::from_str::<serde_json::Value>(&error_body) {
-                        if let Some(error_code) = error_json.get("error").and_then(|e| e.as_str()) {
-                            match error_code {
-                                "authorization_pending" => {
-                                    println!("Waiting for user to authorize...");
-                                    continue;
-                                }
-                                "slow_down" => {
-                                    println!("Server requested to slow down polling.");
-                                    continue;
-                                }
-                                "expired_token" => {
-                                    return Err(anyhow::anyhow!(
-                                        "Authentication expired. Please try again."
-                                    ));
-                                }
-                                _ => {
-                                    return Err(anyhow::anyhow!(
-                                        "Authentication failed with error '{}': {}",
-                                        error_code,
-                                        error_body
-                                    ));
-                                }
-                            }
+                if status == reqwest::StatusCode::BAD_REQUEST
+                    && let Ok(error_json) = serde_json::from_str::<serde_json::Value>(&error_body)
+                    && let Some(error_code) = error_json.get("error").and_then(|e| e.as_str()) {
+                    match error_code {
+                        "authorization_pending" => {
+                            println!("Waiting for user to authorize...");
+                            continue;
+                        }
+                        "slow_down" => {
+                            println!("Server requested to slow down polling.");
+                            continue;
+                        }
+                        "expired_token" => {
+                            return Err(anyhow::anyhow!(
+                                "Authentication expired. Please try again."
+                            ));
+                        }
+                        _ => {
+                            return Err(anyhow::anyhow!(
+                                "Authentication failed with error '{}': {}",
+                                error_code,
+                                error_body
+                            ));
                         }
                     }
                 }
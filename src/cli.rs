@@ -1,3 +1,6 @@
+use crate::loader::LoaderSpec;
+use crate::log4j::{LogFormat, LogLevel};
+use crate::mirror::DownloadSource;
 use clap::{Parser, Subcommand, ValueEnum};
 
 #[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, ValueEnum, Debug)]
@@ -20,29 +23,82 @@ pub struct Cli {
     #[command(subcommand)]
     pub command: Commands,
 
+    /// Minecraft directory (defaults to MCLC_HOME, then minecraft_dir in config.json, then the platform data directory)
+    #[arg(long = "minecraft-dir", value_name = "DIR", global = true)]
+    pub minecraft_dir: Option<String>,
+
     /// Specify Java runtime path
     #[arg(long = "runtime", short = 'r', value_name = "PATH", global = true)]
     pub java_runtime_path: Option<String>,
+
+    /// How many times a failed download is retried
+    #[arg(long = "retries", value_name = "COUNT", global = true)]
+    pub download_retries: Option<u32>,
+
+    /// Download source: official, bmclapi or a mirror URL (falls back to the others on failure)
+    #[arg(long = "source", value_name = "SOURCE", global = true)]
+    pub source: Option<DownloadSource>,
+
+    /// Work without network: reuse installed files, cached manifest and cached credentials
+    #[arg(long = "offline", global = true)]
+    pub offline: bool,
 }
 
 #[derive(Subcommand)]
 pub enum Commands {
     /// List all available Minecraft versions
-    #[command(long_about = "Show official version list, including version type (release/snapshot/etc)")]
-    List,
+    #[command(long_about = "Show official version list with version type (release/snapshot/etc) and release date. Installed versions are marked with '*'")]
+    List {
+        /// Only show versions of this type
+        #[arg(long = "type", value_name = "TYPE", value_parser = ["release", "snapshot", "old_beta", "old_alpha"])]
+        version_type: Option<String>,
+
+        /// Only show installed versions (works offline)
+        #[arg(long)]
+        installed: bool,
+
+        /// Only show versions whose id contains this text
+        #[arg(long, value_name = "PATTERN")]
+        search: Option<String>,
+    },
 
     /// Install specified Minecraft version
     #[command(long_about = "Download all necessary files for the specified version, including client JAR, library files, and asset files")]
     Install {
-        /// Version to install (e.g., 1.21.11, etc.)
+        /// Version to install (e.g., 1.21.11, latest, latest-snapshot)
+        version: String,
+
+        /// Also install a mod loader: fabric or quilt, optionally with a loader version (fabric@0.16.9),
+        /// or forge/neoforge with a required version (forge@47.2.0, neoforge@21.1.77)
+        #[arg(long, value_name = "LOADER[@VERSION]")]
+        loader: Option<LoaderSpec>,
+
+        /// Install Forge or NeoForge from an installer jar or its Maven coordinate
+        #[arg(long, value_name = "JAR|COORDINATE", conflicts_with = "loader")]
+        installer: Option<String>,
+    },
+
+    /// Check an installed version for missing or corrupt files
+    #[command(long_about = "Check the client JAR, libraries, natives, asset index and asset objects of an installed version against the sizes and SHA-1 hashes in its version JSON")]
+    Verify {
+        /// Version to verify
         version: String,
+
+        /// Re-download missing and corrupt files
+        #[arg(long)]
+        repair: bool,
     },
 
     /// Launch specified Minecraft version
     #[command(long_about = "Run installed Minecraft version. If using Microsoft authentication, run the login command first")]
     Launch {
-        /// Version to launch
-        version: String,
+        /// Version to launch (also accepts latest, latest-snapshot)
+        #[arg(required_unless_present = "instance")]
+        version: Option<String>,
+
+        /// Launch an instance with its own game directory and settings
+        #[arg(long, value_name = "NAME", conflicts_with = "version")]
+        instance: Option<String>,
 
         /// Game username (required for offline mode)
         #[arg(short = 'u', long)]
@@ -53,7 +109,7 @@ pub enum Commands {
         access_token: Option<String>,
 
         /// Custom JVM arguments (e.g., -Xmx4G -XX:+UseG1GC)
-        #[arg(short = 'j', long, value_name = "ARGS")]
+        #[arg(short = 'j', long, value_name = "ARGS", allow_hyphen_values = true)]
         jvm_args: Option<String>,
 
         /// Authentication type
@@ -67,6 +123,60 @@ pub enum Commands {
         /// Path to authlib-injector.jar (for external auth, skips auto-download)
         #[arg(long = "authlib-jar")]
         authlib_jar: Option<String>,
+
+        /// Start the game in demo mode
+        #[arg(long)]
+        demo: bool,
+
+        /// Stay attached to the game: stream its output, pass Ctrl-C on to it and exit with its exit code
+        #[arg(long)]
+        wait: bool,
+
+        /// How --wait prints the game's log
+        #[arg(long, value_enum, value_name = "FORMAT", requires = "wait")]
+        log_format: Option<LogFormat>,
+
+        /// Leave out game log events below this level (with --wait)
+        #[arg(long, value_enum, value_name = "LEVEL", requires = "wait")]
+        log_level: Option<LogLevel>,
+
+        /// Game window width
+        #[arg(long, requires = "height")]
+        width: Option<u32>,
+
+        /// Game window height
+        #[arg(long, requires = "width")]
+        height: Option<u32>,
+
+        /// Join a singleplayer world right after start-up
+        #[arg(long = "quick-play-singleplayer", value_name = "WORLD", group = "quick_play")]
+        quick_play_singleplayer: Option<String>,
+
+        /// Join a server right after start-up
+        #[arg(long = "quick-play-multiplayer", value_name = "ADDRESS", group = "quick_play")]
+        quick_play_multiplayer: Option<String>,
+
+        /// Join a realm right after start-up
+        #[arg(long = "quick-play-realms", value_name = "REALM_ID", group = "quick_play")]
+        quick_play_realms: Option<String>,
+    },
+
+    /// Manage instances: versions with their own game directory and settings
+    Instance {
+        #[command(subcommand)]
+        command: InstanceCommand,
+    },
+
+    /// Install modpacks into instances
+    Modpack {
+        #[command(subcommand)]
+        command: ModpackCommand,
+    },
+
+    /// Find Java installations
+    Java {
+        #[command(subcommand)]
+        command: JavaCommand,
     },
 
     /// Login to Microsoft account
@@ -88,3 +198,117 @@ pub enum Commands {
         api_url: String,
     },
 }
+
+#[derive(Subcommand)]
+pub enum InstanceCommand {
+    /// Create an instance, or replace the settings of an existing one
+    #[command(long_about = "Create a named instance of an installed version with its own game directory (saves, mods, options) and launch settings. Libraries and assets stay shared with all other versions")]
+    Create {
+        /// Instance name
+        name: String,
+
+        /// Version the instance launches
+        #[arg(long)]
+        version: String,
+
+        /// Game directory (defaults to instances/<NAME> in the Minecraft directory)
+        #[arg(long, value_name = "DIR")]
+        game_dir: Option<String>,
+
+        /// JVM arguments passed on every launch
+        #[arg(short = 'j', long, value_name = "ARGS", allow_hyphen_values = true)]
+        jvm_args: Option<String>,
+
+        /// Java runtime for this instance
+        #[arg(long = "java", value_name = "PATH")]
+        java_path: Option<String>,
+
+        /// Maximum heap size (e.g., 4G, 6144M)
+        #[arg(long, value_name = "SIZE", value_parser = parse_memory)]
+        memory: Option<String>,
+
+        /// Game window width
+        #[arg(long, requires = "height")]
+        width: Option<u32>,
+
+        /// Game window height
+        #[arg(long, requires = "width")]
+        height: Option<u32>,
+    },
+
+    /// List instances
+    List,
+
+    /// Remove an instance (its game directory is kept)
+    Remove {
+        /// Instance name
+        name: String,
+    },
+}
+
+fn parse_memory(value: &str) -> Result<String, String> {
+    let digits = value.trim_end_matches(['k', 'K', 'm', 'M', 'g', 'G']);
+    if digits.is_empty() || value.len() - digits.len() > 1 || !digits.chars().all(|c| c.is_ascii_digit()) {
+        return Err(format!("'{}' is not a heap size like 4G or 6144M", value));
+    }
+    Ok(value.to_string())
+}
+
+#[derive(Subcommand)]
+pub enum JavaCommand {
+    /// List the Java installations on this machine
+    #[command(long_about = "List the Java installations found through JAVA_HOME, PATH, /usr/lib/jvm, /opt, SDKMAN, asdf, ~/.jdks and the runtime directory, with their version, architecture and vendor")]
+    List,
+
+    /// Download one of Mojang's Java runtimes
+    #[command(long_about = "Download one of the Java runtimes Mojang publishes for the official launcher into the runtime directory, verifying every file. Versions name the runtime they need in javaVersion.component")]
+    Install {
+        /// Runtime component, e.g. java-runtime-delta, java-runtime-gamma or jre-legacy
+        component: String,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum ModpackCommand {
+    /// Install a Modrinth .mrpack or a CurseForge modpack zip into an instance
+    #[command(long_about = "Install the Minecraft version and mod loader a Modrinth .mrpack or CurseForge modpack zip needs, download its client files and extract its overrides into the instance's game directory. Installing a newer version of the pack into the same instance updates it")]
+    Install {
+        /// Path to the .mrpack or CurseForge .zip file
+        file: String,
+
+        /// Instance to install into (created when missing)
+        #[arg(long)]
+        instance: String,
+    },
+
+    /// Export an instance as a Modrinth .mrpack
+    #[command(long_about = "Write an instance as a Modrinth .mrpack. Files still matching the modpack the instance was installed from are listed with their download URL when it is on a host Modrinth accepts (cdn.modrinth.com, github.com, raw.githubusercontent.com, gitlab.com). Everything else, including mods added by hand and files from CurseForge packs, is put into overrides. Logs, crash reports, saves and screenshots are left out unless --include names them")]
+    Export {
+        /// Instance to export
+        instance: String,
+
+        /// Output file (defaults to <INSTANCE>.mrpack)
+        #[arg(short, long, value_name = "FILE")]
+        output: Option<String>,
+
+        /// Pack name (defaults to the installed modpack's name or the instance name)
+        #[arg(long)]
+        name: Option<String>,
+
+        /// Pack version (defaults to the installed modpack's version or 1.0.0)
+        #[arg(long = "pack-version", value_name = "VERSION")]
+        pack_version: Option<String>,
+
+        /// Version the instance runs, for the Minecraft and loader dependencies (defaults to the installed modpack's)
+        #[arg(long = "version-id", value_name = "ID")]
+        version_id: Option<String>,
+
+        /// Only export files matching this glob (repeatable)
+        #[arg(long, value_name = "GLOB")]
+        include: Vec<String>,
+
+        /// Leave out files matching this glob (repeatable)
+        #[arg(long, value_name = "GLOB")]
+        exclude: Vec<String>,
+    },
+}
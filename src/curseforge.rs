@@ -0,0 +1,41 @@
+use crate::models::{CurseForgeFile, CurseForgeResponse};
+use anyhow::Context;
+use reqwest::Client;
+
+pub const CURSEFORGE_API_URL: &str = "https://api.curseforge.com";
+
+/// The CurseForge API, or a stand-in serving the same routes.
+#[derive(Debug, Clone)]
+pub struct CurseForgeApi {
+    pub api_url: String,
+    pub api_key: Option<String>,
+}
+
+impl CurseForgeApi {
+    /// Looks up one file of a project.
+    pub async fn file(&self, client: &Client, project_id: u64, file_id: u64) -> anyhow::Result<CurseForgeFile> {
+        if self.api_key.is_none() && self.api_url == CURSEFORGE_API_URL {
+            anyhow::bail!("The CurseForge API needs a key, set curseforge_api_key in config.json");
+        }
+        let url = format!("{}/v1/mods/{}/files/{}", self.api_url.trim_end_matches('/'), project_id, file_id);
+        let mut request = client.get(&url);
+        if let Some(api_key) = &self.api_key {
+            request = request.header("x-api-key", api_key);
+        }
+        let response: CurseForgeResponse<CurseForgeFile> = request
+            .send()
+            .await?
+            .error_for_status()
+            .with_context(|| format!("Failed to look up file {} of CurseForge project {}", file_id, project_id))?
+            .json()
+            .await
+            .with_context(|| format!("Failed to parse {}", url))?;
+        Ok(response.data)
+    }
+}
+
+impl CurseForgeFile {
+    pub fn sha1(&self) -> Option<&str> {
+        self.hashes.iter().find(|hash| hash.algo == 1).map(|hash| hash.value.as_str())
+    }
+}
@@ -1,17 +1,48 @@
+use crate::install::FailedDownload;
+use std::path::PathBuf;
 use thiserror::Error;
 
 #[derive(Error, Debug)]
+#[allow(clippy::enum_variant_names)]
 pub enum LauncherError {
     #[error("Version {0} not found")]
-    #[allow(dead_code)]
     VersionNotFound(String),
 
-    #[error("Java not found. Please set JAVA_HOME or use --runtime")]
-    #[allow(dead_code)]
-    JavaNotFound,
+    #[error("Version inheritance cycle: {}", .0.join(" -> "))]
+    InheritanceCycle(Vec<String>),
+
+    #[error("Java {0} not found. Please install it, set JAVA_HOME or use --runtime")]
+    JavaNotFound(u32),
+
+    #[error("{} is Java {found}, but this version needs Java {required} or newer", .path.display())]
+    JavaTooOld {
+        path: PathBuf,
+        found: u32,
+        required: u32,
+    },
 
     #[error("Authentication required but not found. Please run 'mclc login'.")]
     AuthNotFound,
+
+    #[error("{0} file(s) are missing and cannot be downloaded while offline")]
+    MissingWhileOffline(usize),
+
+    #[error("Size mismatch for {}: expected {expected} bytes, got {actual}", .path.display())]
+    SizeMismatch {
+        path: PathBuf,
+        expected: u64,
+        actual: u64,
+    },
+
+    #[error("SHA-1 mismatch for {}: expected {expected}, got {actual}", .path.display())]
+    ChecksumMismatch {
+        path: PathBuf,
+        expected: String,
+        actual: String,
+    },
+
+    #[error("{} file(s) failed to download, run the command again to retry", .0.len())]
+    DownloadsFailed(Vec<FailedDownload>),
 }
 
 #[allow(dead_code)]
@@ -0,0 +1,383 @@
+use crate::install::Installer;
+use crate::maven;
+use crate::models::{InstallProfile, Processor};
+use anyhow::Context;
+use regex::Regex;
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+pub const FORGE_MAVEN_URL: &str = "https://maven.minecraftforge.net";
+pub const NEOFORGE_MAVEN_URL: &str = "https://maven.neoforged.net/releases";
+
+/// Maven repository that hosts the installers of a Forge or NeoForge coordinate.
+pub fn repository_for(coordinate: &str) -> &'static str {
+    if coordinate.starts_with("net.neoforged") {
+        NEOFORGE_MAVEN_URL
+    } else {
+        FORGE_MAVEN_URL
+    }
+}
+
+/// `install_profile.json` and the raw version JSON from an installer jar.
+pub fn read_installer(installer_path: &Path) -> anyhow::Result<(InstallProfile, Vec<u8>)> {
+    let file = File::open(installer_path)
+        .with_context(|| format!("Failed to open installer {:?}", installer_path))?;
+    let mut archive = zip::ZipArchive::new(file)
+        .with_context(|| format!("Failed to read installer {:?}", installer_path))?;
+
+    let profile_json = read_entry(&mut archive, "install_profile.json")?;
+    let raw: serde_json::Value = serde_json::from_slice(&profile_json).context("Failed to parse install_profile.json")?;
+    if raw.get("install").is_some() || raw.get("versionInfo").is_some() {
+        anyhow::bail!("Installers from before Minecraft 1.13 (install_profile spec 0) are not supported");
+    }
+    let profile: InstallProfile = serde_json::from_value(raw).context("Failed to parse install_profile.json")?;
+
+    let version_json = read_entry(&mut archive, profile.json.trim_start_matches('/'))?;
+    Ok((profile, version_json))
+}
+
+/// Copies the artifacts bundled under `maven/` in the installer into the libraries directory.
+pub fn extract_bundled_libraries(installer_path: &Path, libraries_dir: &Path) -> anyhow::Result<()> {
+    let mut archive = zip::ZipArchive::new(File::open(installer_path)?)?;
+    for i in 0..archive.len() {
+        let mut entry = archive.by_index(i)?;
+        let Some(relative) = entry.enclosed_name().and_then(|p| p.strip_prefix("maven").ok().map(Path::to_path_buf)) else {
+            continue;
+        };
+        if entry.is_dir() || relative.as_os_str().is_empty() {
+            continue;
+        }
+        let out_path = libraries_dir.join(relative);
+        if let Some(parent) = out_path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let mut out = File::create(&out_path).with_context(|| format!("Failed to create {:?}", out_path))?;
+        std::io::copy(&mut entry, &mut out)?;
+    }
+    Ok(())
+}
+
+/// Everything the processors of one installer run against.
+pub struct ProcessorRun<'a> {
+    pub installer_path: &'a Path,
+    pub profile: &'a InstallProfile,
+    pub java_path: &'a Path,
+    pub minecraft_dir: &'a Path,
+    pub libraries_dir: &'a Path,
+    pub minecraft_jar: &'a Path,
+    /// Scratch directory for data files unpacked from the installer
+    pub work_dir: &'a Path,
+}
+
+impl ProcessorRun<'_> {
+    /// Runs the client-side processors in order, skipping those whose outputs are already in place.
+    pub async fn run(&self) -> anyhow::Result<()> {
+        let data = self.data_values()?;
+        let processors: Vec<&Processor> = self
+            .profile
+            .processors
+            .iter()
+            .filter(|p| p.sides.as_ref().is_none_or(|sides| sides.iter().any(|s| s == "client")))
+            .collect();
+
+        for (index, processor) in processors.iter().enumerate() {
+            let outputs = processor
+                .outputs
+                .iter()
+                .map(|(path, sha1)| Ok((self.substitute(path, &data)?, self.substitute(sha1, &data)?)))
+                .collect::<anyhow::Result<Vec<(String, String)>>>()?;
+            if !outputs.is_empty() && outputs.iter().all(|(path, sha1)| output_matches(path, sha1)) {
+                println!("Processor {}/{} ({}) is up to date", index + 1, processors.len(), processor.jar);
+                continue;
+            }
+
+            println!("Running processor {}/{}: {}", index + 1, processors.len(), processor.jar);
+            self.run_processor(processor, &data).await?;
+
+            for (path, expected) in &outputs {
+                let actual = Installer::sha1_file(Path::new(path))
+                    .with_context(|| format!("Processor {} did not produce {}", processor.jar, path))?;
+                if !actual.eq_ignore_ascii_case(expected) {
+                    anyhow::bail!("Processor {} produced {} with SHA-1 {}, expected {}", processor.jar, path, actual, expected);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    async fn run_processor(&self, processor: &Processor, data: &HashMap<String, String>) -> anyhow::Result<()> {
+        let jar = self.library_path(&processor.jar)?;
+        let main_class = main_class(&jar)?;
+        let mut classpath = vec![jar];
+        for entry in &processor.classpath {
+            classpath.push(self.library_path(entry)?);
+        }
+        let classpath = std::env::join_paths(&classpath).context("Invalid processor classpath")?;
+        let args = processor
+            .args
+            .iter()
+            .map(|arg| self.substitute(arg, data))
+            .collect::<anyhow::Result<Vec<_>>>()?;
+
+        let output = tokio::process::Command::new(self.java_path)
+            .arg("-cp")
+            .arg(&classpath)
+            .arg(&main_class)
+            .args(&args)
+            .current_dir(self.minecraft_dir)
+            .output()
+            .await
+            .with_context(|| format!("Failed to start {:?}", self.java_path))?;
+        if !output.status.success() {
+            eprintln!("{}", String::from_utf8_lossy(&output.stdout));
+            eprintln!("{}", String::from_utf8_lossy(&output.stderr));
+            anyhow::bail!("Processor {} failed with {}", processor.jar, output.status);
+        }
+        Ok(())
+    }
+
+    /// The `{KEY}` values processors can refer to: the built-ins plus the client side of `data`.
+    fn data_values(&self) -> anyhow::Result<HashMap<String, String>> {
+        let path_string = |p: &Path| p.to_string_lossy().to_string();
+        let mut values = HashMap::from([
+            ("SIDE".to_string(), "client".to_string()),
+            ("MINECRAFT_JAR".to_string(), path_string(self.minecraft_jar)),
+            ("MINECRAFT_VERSION".to_string(), self.profile.minecraft.clone()),
+            ("ROOT".to_string(), path_string(self.minecraft_dir)),
+            ("INSTALLER".to_string(), path_string(self.installer_path)),
+            ("LIBRARY_DIR".to_string(), path_string(self.libraries_dir)),
+        ]);
+
+        let mut archive = zip::ZipArchive::new(File::open(self.installer_path)?)?;
+        for (key, value) in &self.profile.data {
+            let value = &value.client;
+            let resolved = if let Some(coordinate) = value.strip_prefix('[').and_then(|v| v.strip_suffix(']')) {
+                path_string(&self.library_path(coordinate)?)
+            } else if let Some(literal) = value.strip_prefix('\'').and_then(|v| v.strip_suffix('\'')) {
+                literal.to_string()
+            } else if let Some(entry) = value.strip_prefix('/') {
+                // A file inside the installer, e.g. the binary patches
+                let out_path = self.work_dir.join(entry);
+                if let Some(parent) = out_path.parent() {
+                    fs::create_dir_all(parent)?;
+                }
+                fs::write(&out_path, read_entry(&mut archive, entry)?)?;
+                path_string(&out_path)
+            } else {
+                value.clone()
+            };
+            values.insert(key.clone(), resolved);
+        }
+        Ok(values)
+    }
+
+    /// Replaces `{KEY}` with data values; an argument that is a whole `[coordinate]` becomes its library path.
+    fn substitute(&self, arg: &str, data: &HashMap<String, String>) -> anyhow::Result<String> {
+        if let Some(coordinate) = arg.strip_prefix('[').and_then(|a| a.strip_suffix(']')) {
+            return Ok(self.library_path(coordinate)?.to_string_lossy().to_string());
+        }
+        let placeholder = Regex::new(r"\{(\w+)\}").expect("valid regex");
+        let mut missing = None;
+        let result = placeholder.replace_all(arg, |caps: &regex::Captures| match data.get(&caps[1]) {
+            Some(value) => value.clone(),
+            None => {
+                missing = Some(caps[1].to_string());
+                caps[0].to_string()
+            }
+        });
+        match missing {
+            Some(key) => anyhow::bail!("Unknown processor data key {{{}}}", key),
+            None => Ok(result.into_owned()),
+        }
+    }
+
+    fn library_path(&self, coordinate: &str) -> anyhow::Result<PathBuf> {
+        let path = maven::artifact_path(coordinate).with_context(|| format!("Invalid Maven coordinate {}", coordinate))?;
+        Ok(self.libraries_dir.join(path))
+    }
+}
+
+fn output_matches(path: &str, sha1: &str) -> bool {
+    Installer::sha1_file(Path::new(path)).is_ok_and(|actual| actual.eq_ignore_ascii_case(sha1))
+}
+
+/// `Main-Class` from a jar's manifest.
+fn main_class(jar: &Path) -> anyhow::Result<String> {
+    let mut archive = zip::ZipArchive::new(File::open(jar).with_context(|| format!("Failed to open {:?}", jar))?)?;
+    let manifest = String::from_utf8_lossy(&read_entry(&mut archive, "META-INF/MANIFEST.MF")?).to_string();
+    manifest
+        .lines()
+        .find_map(|line| line.strip_prefix("Main-Class:"))
+        .map(|class| class.trim().to_string())
+        .with_context(|| format!("{:?} has no Main-Class", jar))
+}
+
+fn read_entry(archive: &mut zip::ZipArchive<File>, name: &str) -> anyhow::Result<Vec<u8>> {
+    let mut entry = archive.by_name(name).with_context(|| format!("Installer has no {}", name))?;
+    let mut bytes = Vec::new();
+    entry.read_to_end(&mut bytes)?;
+    Ok(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::install::tests::sha1_hex;
+    use std::io::Write;
+
+    fn installer_jar(dir: &Path, entries: &[(&str, &[u8])]) -> PathBuf {
+        let path = dir.join("installer.jar");
+        let mut zip = zip::ZipWriter::new(File::create(&path).unwrap());
+        for (name, content) in entries {
+            zip.start_file(*name, zip::write::FileOptions::default()).unwrap();
+            zip.write_all(content).unwrap();
+        }
+        zip.finish().unwrap();
+        path
+    }
+
+    fn profile(value: serde_json::Value) -> InstallProfile {
+        serde_json::from_value(value).unwrap()
+    }
+
+    fn processor_run<'a>(dir: &'a Path, installer_path: &'a Path, profile: &'a InstallProfile) -> ProcessorRun<'a> {
+        ProcessorRun {
+            installer_path,
+            profile,
+            java_path: Path::new("java"),
+            minecraft_dir: dir,
+            libraries_dir: Path::new("/libraries"),
+            minecraft_jar: Path::new("/versions/1.20.1/1.20.1.jar"),
+            work_dir: Path::new("/work"),
+        }
+    }
+
+    #[test]
+    fn read_installer_profile_and_version_json() {
+        let dir = tempfile::tempdir().unwrap();
+        let profile = br#"{"spec": 1, "minecraft": "1.20.1", "json": "/version.json",
+            "data": {"MAPPINGS": {"client": "[de.oceanlabs.mcp:mcp_config:1.20.1:mappings@txt]", "server": "x"}},
+            "processors": [{"jar": "net.minecraftforge:installertools:1.3.0", "args": ["--task", "MCP_DATA"]}]}"#;
+        let version = br#"{"id": "1.20.1-forge-47.2.0"}"#;
+        let path = installer_jar(dir.path(), &[("install_profile.json", profile), ("version.json", version)]);
+
+        let (profile, version_json) = read_installer(&path).unwrap();
+        assert_eq!(profile.minecraft, "1.20.1");
+        assert_eq!(profile.data["MAPPINGS"].client, "[de.oceanlabs.mcp:mcp_config:1.20.1:mappings@txt]");
+        assert_eq!(profile.processors[0].jar, "net.minecraftforge:installertools:1.3.0");
+        assert_eq!(version_json, version);
+    }
+
+    #[test]
+    fn read_installer_rejects_spec_0() {
+        let dir = tempfile::tempdir().unwrap();
+        let profile = br#"{"install": {"profileName": "Forge", "target": "1.12.2-forge-14.23.5.2860"}, "versionInfo": {}}"#;
+        let path = installer_jar(dir.path(), &[("install_profile.json", profile)]);
+
+        let error = read_installer(&path).unwrap_err();
+        assert!(error.to_string().contains("spec 0"), "{}", error);
+    }
+
+    #[test]
+    fn data_values_and_substitute() {
+        let dir = tempfile::tempdir().unwrap();
+        let installer_path = installer_jar(dir.path(), &[("data/client.lzma", b"patches")]);
+        let profile = profile(serde_json::json!({
+            "minecraft": "1.20.1",
+            "json": "/version.json",
+            "data": {
+                "MAPPINGS": {"client": "[de.oceanlabs.mcp:mcp_config:1.20.1:mappings@txt]", "server": "x"},
+                "MAPPINGS_SHA": {"client": "'3e2f9c3a'", "server": "x"},
+                "BINPATCH": {"client": "/data/client.lzma", "server": "/data/server.lzma"}
+            }
+        }));
+        let work_dir = dir.path().join("work");
+        let run = ProcessorRun { work_dir: &work_dir, ..processor_run(dir.path(), &installer_path, &profile) };
+
+        let data = run.data_values().unwrap();
+        assert_eq!(data["SIDE"], "client");
+        assert_eq!(data["MINECRAFT_VERSION"], "1.20.1");
+        assert_eq!(
+            data["MAPPINGS"],
+            "/libraries/de/oceanlabs/mcp/mcp_config/1.20.1/mcp_config-1.20.1-mappings.txt"
+        );
+        assert_eq!(data["MAPPINGS_SHA"], "3e2f9c3a");
+        assert_eq!(Path::new(&data["BINPATCH"]), work_dir.join("data/client.lzma"));
+        assert_eq!(fs::read(work_dir.join("data/client.lzma")).unwrap(), b"patches");
+
+        assert_eq!(run.substitute("--side={SIDE}", &data).unwrap(), "--side=client");
+        assert_eq!(run.substitute("{MINECRAFT_JAR}", &data).unwrap(), "/versions/1.20.1/1.20.1.jar");
+        assert_eq!(
+            run.substitute("[net.minecraft:client:1.20.1:srg]", &data).unwrap(),
+            "/libraries/net/minecraft/client/1.20.1/client-1.20.1-srg.jar"
+        );
+        assert!(run.substitute("{PATCHED}", &data).is_err());
+    }
+
+    #[tokio::test]
+    async fn processors_skip_server_side_and_up_to_date_outputs() {
+        let dir = tempfile::tempdir().unwrap();
+        let installer_path = installer_jar(dir.path(), &[]);
+        let output = dir.path().join("client-srg.jar");
+        fs::write(&output, b"patched").unwrap();
+        let output_key = output.to_string_lossy().to_string();
+        // The processor jars do not exist, so any processor that is not skipped fails
+        let profile = profile(serde_json::json!({
+            "minecraft": "1.20.1",
+            "json": "/version.json",
+            "data": {"PATCHED_SHA": {"client": format!("'{}'", sha1_hex(b"patched")), "server": "x"}},
+            "processors": [
+                {"jar": "net.minecraftforge:jarsplitter:1.1.4", "sides": ["server"]},
+                {"jar": "net.minecraftforge:binarypatcher:1.1.1", "sides": ["client"], "outputs": {output_key.clone(): "{PATCHED_SHA}"}}
+            ]
+        }));
+        processor_run(dir.path(), &installer_path, &profile).run().await.unwrap();
+
+        fs::write(&output, b"stale").unwrap();
+        let error = processor_run(dir.path(), &installer_path, &profile).run().await.unwrap_err();
+        assert!(format!("{:#}", error).contains("binarypatcher"), "{:#}", error);
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn processor_output_with_wrong_sha1_fails() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = tempfile::tempdir().unwrap();
+        let libraries_dir = dir.path().join("libraries");
+        let jar_dir = libraries_dir.join("net/minecraftforge/binarypatcher/1.1.1");
+        fs::create_dir_all(&jar_dir).unwrap();
+        let jar = installer_jar(dir.path(), &[("META-INF/MANIFEST.MF", b"Main-Class: net.minecraftforge.binarypatcher.ConsoleTool\n")]);
+        fs::rename(jar, jar_dir.join("binarypatcher-1.1.1.jar")).unwrap();
+        let installer_path = installer_jar(dir.path(), &[]);
+        // Stands in for java: writes its last argument with the wrong content
+        let java = dir.path().join("java");
+        fs::write(&java, "#!/bin/sh\nfor last; do :; done\nprintf corrupt > \"$last\"\n").unwrap();
+        fs::set_permissions(&java, fs::Permissions::from_mode(0o755)).unwrap();
+
+        let output = dir.path().join("client-srg.jar");
+        let output_key = output.to_string_lossy().to_string();
+        let profile = profile(serde_json::json!({
+            "minecraft": "1.20.1",
+            "json": "/version.json",
+            "data": {"PATCHED_SHA": {"client": format!("'{}'", sha1_hex(b"patched")), "server": "x"}},
+            "processors": [{
+                "jar": "net.minecraftforge:binarypatcher:1.1.1",
+                "args": ["--output", output_key.clone()],
+                "outputs": {output_key: "{PATCHED_SHA}"}
+            }]
+        }));
+        let run = ProcessorRun {
+            java_path: &java,
+            libraries_dir: &libraries_dir,
+            ..processor_run(dir.path(), &installer_path, &profile)
+        };
+
+        let error = run.run().await.unwrap_err();
+        assert_eq!(fs::read(&output).unwrap(), b"corrupt");
+        let expected = format!("with SHA-1 {}, expected {}", sha1_hex(b"corrupt"), sha1_hex(b"patched"));
+        assert!(error.to_string().contains(&expected), "{}", error);
+    }
+}
@@ -1,24 +1,68 @@
+use crate::error::LauncherError;
+use crate::forge;
+use crate::java;
+use crate::loader::{Loader, LoaderSpec};
+use crate::maven;
+use crate::mirror::DownloadSource;
 use crate::models::*;
+use crate::rules::{Features, RuleContext};
+use crate::version;
 use anyhow::Context;
 use futures_util::stream::{self, StreamExt};
 use indicatif::{ProgressBar, ProgressStyle};
 use reqwest::Client;
-use std::collections::{HashMap, HashSet};
+use serde::de::DeserializeOwned;
+use sha1::{Digest, Sha1};
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::fs::{self, File};
 use std::io::Write;
 use std::path::Path;
 use std::sync::{Arc, atomic::{AtomicU64, Ordering}};
+use std::time::Duration;
 
-const VERSION_MANIFEST_URL: &str = "https://launchermeta.mojang.com/mc/game/version_manifest.json";
+const VERSION_MANIFEST_URL: &str = "https://piston-meta.mojang.com/mc/game/version_manifest_v2.json";
+const MANIFEST_CACHE_FILE: &str = "version_manifest_v2.json";
+const MANIFEST_CACHE_META_FILE: &str = "version_manifest_v2.meta.json";
+const JAVA_RUNTIME_INDEX_URL: &str =
+    "https://launchermeta.mojang.com/v1/products/java-runtime/2ec0cc96c44e5a76b9c8b7c39df7210883d12871/all.json";
 const ASSET_BASE_URL: &str = "https://resources.download.minecraft.net";
 const MAVEN_BASE_URL: &str = "https://repo1.maven.org/maven2";
 const MAX_CONCURRENT_DOWNLOADS: usize = 16;
+pub const DEFAULT_DOWNLOAD_RETRIES: u32 = 3;
+const RETRY_BASE_DELAY: Duration = Duration::from_millis(500);
+const RETRY_MAX_DELAY: Duration = Duration::from_secs(30);
+const CONNECTIVITY_TIMEOUT: Duration = Duration::from_secs(5);
 
 #[derive(Debug, Clone)]
-struct DownloadTask {
+pub struct DownloadTask {
     url: String,
     path: std::path::PathBuf,
     task_type: String,
+    sha1: Option<String>,
+    size: Option<u64>,
+}
+
+impl DownloadTask {
+    pub fn new(url: String, path: std::path::PathBuf, task_type: &str, sha1: Option<String>, size: Option<u64>) -> Self {
+        DownloadTask { url, path, task_type: task_type.to_string(), sha1, size }
+    }
+}
+
+/// A download that still failed after all retries.
+#[derive(Debug)]
+pub struct FailedDownload {
+    pub task: DownloadTask,
+    pub error: anyhow::Error,
+}
+
+/// Filters for `mclc list`.
+#[derive(Debug, Default)]
+pub struct ListOptions {
+    pub version_type: Option<String>,
+    /// Only list installed versions, without fetching the manifest
+    pub installed: bool,
+    /// Case-insensitive substring of the version id
+    pub search: Option<String>,
 }
 
 #[derive(Debug)]
@@ -27,128 +71,622 @@ pub struct Installer {
     pub libraries_dir: std::path::PathBuf,
     pub assets_objects_dir: std::path::PathBuf,
     pub assets_indexes_dir: std::path::PathBuf,
+    pub log_configs_dir: std::path::PathBuf,
+    /// How many times a failed download is retried before giving up
+    pub max_retries: u32,
+    /// Download sources in the order they are tried
+    pub sources: Vec<DownloadSource>,
+    /// Where the version manifest is cached between runs
+    pub cache_dir: std::path::PathBuf,
+    /// Where Mojang Java runtimes are installed
+    pub runtime_dir: std::path::PathBuf,
+    /// Never touch the network, only use installed files and the cached manifest
+    pub offline: bool,
+    /// Base URL of the Fabric meta API
+    pub fabric_meta_url: String,
+    /// Base URL of the Quilt meta API
+    pub quilt_meta_url: String,
+    /// Java used to run Forge installer processors (found like the launch Java when not set)
+    pub java_path: Option<std::path::PathBuf>,
 }
 
 impl Installer {
-    pub async fn list_versions(&self) -> anyhow::Result<()> {
-        println!("Fetching available Minecraft versions...");
-        let client = Client::new();
-        let manifest: VersionManifest = client
-            .get(VERSION_MANIFEST_URL)
-            .send()
-            .await?
-            .json()
-            .await?;
-        println!("Available versions:");
-        for version in &manifest.versions {
-            println!("  {} ({})", version.id, version.version_type);
+    pub async fn list_versions(&self, options: &ListOptions) -> anyhow::Result<()> {
+        let installed = self.installed_versions();
+
+        // (id, type, release time)
+        let mut versions: Vec<(String, String, String)> = if options.installed {
+            let mut versions: Vec<_> = installed
+                .iter()
+                .map(|(id, details)| {
+                    (id.clone(), details.version_type.clone(), details.release_time.clone().unwrap_or_default())
+                })
+                .collect();
+            versions.sort_by(|a, b| b.2.cmp(&a.2));
+            versions
+        } else {
+            println!("Fetching available Minecraft versions...");
+            let manifest = self.fetch_manifest(&Client::new()).await?;
+            println!("Latest release: {}, latest snapshot: {}", manifest.latest.release, manifest.latest.snapshot);
+            manifest
+                .versions
+                .into_iter()
+                .map(|v| (v.id, v.version_type, v.release_time))
+                .collect()
+        };
+
+        if let Some(version_type) = &options.version_type {
+            versions.retain(|(_, t, _)| t == version_type);
+        }
+        if let Some(search) = &options.search {
+            let search = search.to_lowercase();
+            versions.retain(|(id, _, _)| id.to_lowercase().contains(&search));
+        }
+
+        if versions.is_empty() {
+            println!("No matching versions");
+            return Ok(());
+        }
+
+        if options.installed {
+            println!("Installed versions:");
+        } else {
+            println!("Available versions (* = installed):");
+        }
+        for (id, version_type, release_time) in &versions {
+            let marker = if installed.contains_key(id) { '*' } else { ' ' };
+            let date = release_time.get(..10).unwrap_or(release_time);
+            println!("{} {:<28} {:<10} {}", marker, id, version_type, date);
         }
         Ok(())
     }
 
-    pub async fn install_version(&self, version_id: &str) -> anyhow::Result<()> {
-        println!("Installing Minecraft version: {}", version_id);
+    /// Resolves the `latest` and `latest-snapshot` aliases; any other id is returned as is.
+    pub async fn resolve_version_id(&self, version_id: &str) -> anyhow::Result<String> {
+        if version_id != "latest" && version_id != "latest-snapshot" {
+            return Ok(version_id.to_string());
+        }
+        let manifest = self.fetch_manifest(&Client::new()).await?;
+        let version_info = manifest
+            .find(version_id)
+            .ok_or_else(|| LauncherError::VersionNotFound(version_id.to_string()))?;
+        println!("Resolved {} to {}", version_id, version_info.id);
+        Ok(version_info.id.clone())
+    }
 
+    /// Versions in `versions_dir` with a readable version JSON, by id.
+    fn installed_versions(&self) -> BTreeMap<String, VersionDetails> {
+        let mut installed = BTreeMap::new();
+        let Ok(entries) = fs::read_dir(&self.versions_dir) else {
+            return installed;
+        };
+        for entry in entries.flatten() {
+            let id = entry.file_name().to_string_lossy().to_string();
+            let json_path = entry.path().join(format!("{}.json", id));
+            if let Ok(json) = fs::read_to_string(&json_path)
+                && let Ok(details) = serde_json::from_str::<VersionDetails>(&json) {
+                installed.insert(id, details);
+            }
+        }
+        installed
+    }
+
+    pub async fn install_version(&self, version_id: &str) -> anyhow::Result<()> {
         let client = Client::new();
-        let (_version_info, version_details) = self.fetch_version_details(&client, version_id).await?;
+        let (version_id, version_json) = if self.offline {
+            // The saved version JSON is all there is to go on
+            let version_id = self.resolve_version_id(version_id).await?;
+            let version_json_path = self.versions_dir.join(&version_id).join(format!("{}.json", version_id));
+            let version_json = fs::read(&version_json_path).with_context(|| {
+                format!("Version {} is not installed and cannot be downloaded while offline", version_id)
+            })?;
+            (version_id, version_json)
+        } else {
+            let manifest = self.fetch_manifest(&client).await?;
+            match manifest.find(version_id) {
+                Some(version_info) => {
+                    let version_json_path = self.versions_dir.join(&version_info.id).join(format!("{}.json", version_info.id));
+                    let version_json = self.fetch_version_json(&client, version_info, &version_json_path).await?;
+                    (version_info.id.clone(), version_json)
+                }
+                // Not an official version, but a profile placed in versions/ (e.g. by a mod loader installer)
+                None => {
+                    let version_json_path = self.versions_dir.join(version_id).join(format!("{}.json", version_id));
+                    let version_json = fs::read(&version_json_path)
+                        .map_err(|_| LauncherError::VersionNotFound(version_id.to_string()))?;
+                    (version_id.to_string(), version_json)
+                }
+            }
+        };
+        let version_id = version_id.as_str();
+        println!("Installing Minecraft version: {}", version_id);
 
         let version_dir = self.versions_dir.join(version_id);
+        let version_json_path = version_dir.join(format!("{}.json", version_id));
+        let own_details: VersionDetails = serde_json::from_slice(&version_json)
+            .with_context(|| format!("Failed to parse version JSON of {}", version_id))?;
+
+        // Modded profiles build on another version, which has to be installed first
+        if let Some(parent_id) = &own_details.inherits_from
+            && !self.versions_dir.join(parent_id).join(format!("{}.json", parent_id)).exists() {
+            println!("Version {} inherits from {}", version_id, parent_id);
+            Box::pin(self.install_version(parent_id)).await?;
+        }
+        let version_details = version::resolve_version(&self.versions_dir, own_details)?;
+
         fs::create_dir_all(&version_dir)?;
 
-        // Collect all download tasks
-        let mut tasks = Vec::new();
+        // Download the asset index first, the asset list comes from it
+        if let Some(index_task) = self.asset_index_task(&version_details)
+            && !self.offline
+            && !Self::task_satisfied(&index_task) {
+            println!("Downloading metadata...");
+            Self::download_file(&client, &index_task, &self.sources, self.max_retries)
+                .await
+                .with_context(|| format!("Failed to download {}", index_task.task_type))?;
+        }
+
+        // Now collect client, library and asset tasks
+        let version_natives_dir = version_dir.join("natives");
+        fs::create_dir_all(&version_natives_dir)?;
         let mut extraction_tasks = Vec::new();
-        let mut asset_index_url = None;
-        let mut asset_index_path = None;
+        let mut tasks = self.collect_version_files(&version_details, &version_natives_dir, &mut extraction_tasks);
 
-        // Client JAR
-        if let Some(downloads) = &version_details.downloads {
-            let client_jar_path = version_dir.join(format!("{}.jar", version_id));
-            if !client_jar_path.exists() {
-                tasks.push(DownloadTask {
-                    url: downloads.client.url.clone(),
-                    path: client_jar_path,
-                    task_type: "client".to_string(),
-                });
+        // ARM64 natives for Linux
+        if std::env::consts::OS == "linux" && std::env::consts::ARCH == "aarch64" {
+            self.collect_lwjgl_arm64_download_tasks(&version_details, version_id, &version_natives_dir, &mut tasks, &mut extraction_tasks);
+        }
+
+        tasks.retain(|task| !Self::task_satisfied(task));
+
+        if tasks.is_empty() {
+            println!("All files already downloaded for version {}!", version_id);
+        } else if self.offline {
+            eprintln!("{} file(s) are missing:", tasks.len());
+            for task in &tasks {
+                eprintln!("  [{}] {}", task.task_type, task.path.display());
+            }
+            return Err(LauncherError::MissingWhileOffline(tasks.len()).into());
+        } else {
+            let failures = self.download_tasks(client, tasks).await;
+            if !failures.is_empty() {
+                Self::report_failures(&failures);
+                return Err(LauncherError::DownloadsFailed(failures).into());
             }
         }
 
-        // Asset index URL (always need to check this for assets)
-        if let Some(asset_index) = &version_details.asset_index {
-            let index_path = self.assets_indexes_dir.join(format!("{}.json", asset_index.id));
-            asset_index_url = Some(asset_index.url.clone());
-            asset_index_path = Some(index_path.clone());
+        // Extract native libraries
+        for (jar_path, extract_dir) in extraction_tasks {
+            if jar_path.exists() {
+                self.extract_lwjgl3_native_library(&jar_path, &extract_dir)?;
+            }
         }
 
-        // Download client JAR and asset index first if needed
-        let mut first_phase_tasks = Vec::new();
-        if let Some(ref url) = asset_index_url {
-            if let Some(ref path) = asset_index_path {
-                if !path.exists() {
-                    first_phase_tasks.push(DownloadTask {
-                        url: url.clone(),
-                        path: path.clone(),
-                        task_type: "index".to_string(),
-                    });
-                }
+        // Save the version JSON as published, so its SHA-1 still matches the manifest
+        fs::write(&version_json_path, &version_json)?;
+        version::client_jar(&self.versions_dir, &version_details)?;
+
+        self.ensure_java_runtime(version_details.java_version.as_ref()).await;
+
+        println!("Version {} installed successfully!", version_id);
+        Ok(())
+    }
+
+    /// Installs a Minecraft version with a mod loader on top and returns the id of the loader profile.
+    pub async fn install_loader(&self, game_version: &str, spec: &LoaderSpec) -> anyhow::Result<String> {
+        if self.offline {
+            anyhow::bail!("{} cannot be installed while offline", spec.loader);
+        }
+        let game_version = self.resolve_version_id(game_version).await?;
+
+        if let Some(version) = &spec.version
+            && let Some(coordinate) = spec.loader.installer_coordinate(version, &game_version) {
+            return self.install_forge(&coordinate, Some(&game_version)).await;
+        }
+        let meta_url = match spec.loader {
+            Loader::Fabric => spec.loader.meta_api(&self.fabric_meta_url),
+            Loader::Quilt => spec.loader.meta_api(&self.quilt_meta_url),
+            Loader::Forge | Loader::NeoForge => None,
+        }
+        .ok_or_else(|| anyhow::anyhow!("{} needs a loader version, e.g. --loader {}@<version>", spec.loader, spec.loader.to_string().to_lowercase()))?;
+
+        self.install_version(&game_version).await?;
+        let client = Client::new();
+        let loaders: Vec<LoaderEntry> = self
+            .fetch_json(&client, &format!("{}/versions/loader/{}", meta_url, game_version))
+            .await?;
+        let loader_version = match &spec.version {
+            Some(version) => loaders
+                .iter()
+                .find(|entry| entry.loader.version == *version)
+                .ok_or_else(|| anyhow::anyhow!("{} loader {} is not available for Minecraft {}", spec.loader, version, game_version))?,
+            None => loaders
+                .iter()
+                .find(|entry| entry.loader.stable)
+                .or_else(|| loaders.iter().find(|entry| !entry.loader.version.contains('-')))
+                .or(loaders.first())
+                .ok_or_else(|| anyhow::anyhow!("{} does not support Minecraft {}", spec.loader, game_version))?,
+        }
+        .loader
+        .version
+        .clone();
+
+        println!("Installing {} loader {} for Minecraft {}", spec.loader, loader_version, game_version);
+        let mut profile: serde_json::Value = self
+            .fetch_json(&client, &format!("{}/versions/loader/{}/{}/profile/json", meta_url, game_version, loader_version))
+            .await?;
+        self.complete_maven_libraries(&client, &mut profile).await?;
+
+        let profile_id = profile["id"]
+            .as_str()
+            .map(str::to_string)
+            .unwrap_or_else(|| spec.loader.profile_id(&loader_version, &game_version));
+        let profile_dir = self.versions_dir.join(&profile_id);
+        fs::create_dir_all(&profile_dir)?;
+        fs::write(profile_dir.join(format!("{}.json", profile_id)), serde_json::to_string_pretty(&profile)?)?;
+
+        self.install_version(&profile_id).await?;
+        Ok(profile_id)
+    }
+
+    /// Installs Forge or NeoForge from an installer jar, given as a local path or a Maven coordinate
+    /// (`net.minecraftforge:forge:1.20.1-47.2.0`), runs its processors and returns the new version id.
+    pub async fn install_forge(&self, installer: &str, game_version: Option<&str>) -> anyhow::Result<String> {
+        let client = Client::new();
+        let installer_path = if Path::new(installer).is_file() {
+            std::path::PathBuf::from(installer)
+        } else {
+            if installer.ends_with(".jar") {
+                anyhow::bail!("Installer {} does not exist", installer);
+            }
+            if self.offline {
+                anyhow::bail!("{} cannot be downloaded while offline", installer);
+            }
+            let coordinate = format!("{}:installer", installer);
+            let path = maven::artifact_path(&coordinate)
+                .with_context(|| format!("{} is neither an installer jar nor a Maven coordinate", installer))?;
+            let url = format!("{}/{}", forge::repository_for(installer), path);
+            let sha1 = self
+                .fetch_text(&client, &format!("{}.sha1", url))
+                .await
+                .ok()
+                .and_then(|text| text.split_whitespace().next().map(str::to_string));
+            let task = DownloadTask::new(url, self.libraries_dir.join(&path), "installer", sha1, None);
+            if !Self::task_satisfied(&task) {
+                println!("Downloading installer {}", installer);
+                Self::download_file(&client, &task, &self.sources, self.max_retries)
+                    .await
+                    .with_context(|| format!("Failed to download installer {}", installer))?;
             }
+            task.path
+        };
+
+        let (profile, version_json) = forge::read_installer(&installer_path)?;
+        if let Some(game_version) = game_version
+            && profile.minecraft != game_version {
+            anyhow::bail!("{} is an installer for Minecraft {}, not {}", installer, profile.minecraft, game_version);
         }
+        let version_details: VersionDetails = serde_json::from_slice(&version_json)
+            .context("Failed to parse the installer's version JSON")?;
+        let version_id = version_details.id.clone();
+        println!("Installing {} for Minecraft {}", version_id, profile.minecraft);
 
-        if !first_phase_tasks.is_empty() {
-            println!("Downloading metadata...");
-            let client = Arc::new(client);
-            let results = stream::iter(first_phase_tasks)
-                .map(|task| {
-                    let client = Arc::clone(&client);
-                    let url = task.url.clone();
-                    let path = task.path.clone();
-                    let task_type = task.task_type.clone();
-                    tokio::spawn(async move {
-                        (task_type, Self::download_file(&client, &url, &path).await)
-                    })
-                })
-                .buffer_unordered(MAX_CONCURRENT_DOWNLOADS)
-                .collect::<Vec<_>>()
-                .await;
-
-            for result in results {
-                if let Ok((task_type, download_result)) = result {
-                    if let Err(e) = download_result {
-                        anyhow::bail!("Failed to download {}: {}", task_type, e);
+        // Processors patch the vanilla client, so it has to be complete first
+        self.install_version(&profile.minecraft).await?;
+
+        forge::extract_bundled_libraries(&installer_path, &self.libraries_dir)?;
+        let mut tasks = Vec::new();
+        self.collect_library_download_tasks(&profile.libraries, Path::new(""), &mut tasks, &mut Vec::new());
+        self.collect_library_download_tasks(&version_details.libraries, Path::new(""), &mut tasks, &mut Vec::new());
+        self.download_files(tasks).await?;
+
+        let minecraft_dir = self.versions_dir.parent().unwrap_or(&self.versions_dir);
+        // Processors run on the Java the game itself needs
+        let java_version = version::load_version(&self.versions_dir, &profile.minecraft)?.java_version;
+        let java_path = match &self.java_path {
+            Some(path) => path.clone(),
+            None => java::select_java(&self.runtime_dir, java_version.as_ref())?.path,
+        };
+        let work_dir = tempfile::tempdir().context("Failed to create a temporary directory")?;
+        forge::ProcessorRun {
+            installer_path: &installer_path,
+            profile: &profile,
+            java_path: &java_path,
+            minecraft_dir,
+            libraries_dir: &self.libraries_dir,
+            minecraft_jar: &self.versions_dir.join(&profile.minecraft).join(format!("{}.jar", profile.minecraft)),
+            work_dir: work_dir.path(),
+        }
+        .run()
+        .await?;
+
+        let version_dir = self.versions_dir.join(&version_id);
+        fs::create_dir_all(&version_dir)?;
+        fs::write(version_dir.join(format!("{}.json", version_id)), &version_json)?;
+        self.install_version(&version_id).await?;
+        Ok(version_id)
+    }
+
+    /// Installs Mojang's Java runtime `component` into `runtime/<component>/<platform>/<component>`
+    /// and returns its `java`.
+    pub async fn install_java_runtime(&self, component: &str) -> anyhow::Result<std::path::PathBuf> {
+        let platform = java::runtime_platform().ok_or_else(|| {
+            anyhow::anyhow!("Mojang publishes no Java runtimes for {} {}", std::env::consts::OS, std::env::consts::ARCH)
+        })?;
+        let home = self.runtime_dir.join(component).join(platform).join(component);
+        let java_path = java::runtime_java(&home);
+        if self.offline {
+            if java_path.is_file() {
+                return Ok(java_path);
+            }
+            anyhow::bail!("Java runtime {} is not installed and cannot be downloaded while offline", component);
+        }
+
+        let client = Client::new();
+        let index: JavaRuntimeIndex = self.fetch_json(&client, JAVA_RUNTIME_INDEX_URL).await?;
+        let build = index
+            .get(platform)
+            .and_then(|components| components.get(component))
+            .and_then(|builds| builds.first())
+            .ok_or_else(|| anyhow::anyhow!("Mojang publishes no Java runtime {} for {}", component, platform))?;
+        println!("Installing Java runtime {} ({}) for {}", component, build.version.name, platform);
+
+        let manifest_text = self.fetch_text(&client, &build.manifest.url).await?;
+        let manifest_sha1 = format!("{:x}", Sha1::digest(manifest_text.as_bytes()));
+        if manifest_sha1 != build.manifest.sha1 {
+            return Err(LauncherError::ChecksumMismatch {
+                path: home.clone(),
+                expected: build.manifest.sha1.clone(),
+                actual: manifest_sha1,
+            }
+            .into());
+        }
+        let manifest: JavaRuntimeManifest = serde_json::from_str(&manifest_text)
+            .with_context(|| format!("Failed to parse {}", build.manifest.url))?;
+
+        let mut tasks = Vec::new();
+        let mut executables = Vec::new();
+        let mut links = Vec::new();
+        for (name, file) in &manifest.files {
+            let relative = Path::new(name);
+            if !relative.components().all(|c| matches!(c, std::path::Component::Normal(_))) {
+                anyhow::bail!("Refusing to write {} outside the runtime directory", name);
+            }
+            let path = home.join(relative);
+            match file {
+                JavaRuntimeFile::Directory => fs::create_dir_all(&path)?,
+                JavaRuntimeFile::File { executable, downloads } => {
+                    let raw = &downloads.raw;
+                    tasks.push(DownloadTask::new(raw.url.clone(), path.clone(), "runtime", Some(raw.sha1.clone()), Some(raw.size)));
+                    if *executable {
+                        executables.push(path);
                     }
                 }
+                JavaRuntimeFile::Link { target } => links.push((path, target)),
+            }
+        }
+        self.download_files(tasks).await?;
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            for path in &executables {
+                fs::set_permissions(path, fs::Permissions::from_mode(0o755))
+                    .with_context(|| format!("Failed to make {:?} executable", path))?;
             }
+            for (path, target) in &links {
+                if fs::read_link(path).is_ok_and(|existing| existing == Path::new(target)) {
+                    continue;
+                }
+                if let Some(parent) = path.parent() {
+                    fs::create_dir_all(parent)?;
+                }
+                let _ = fs::remove_file(path);
+                std::os::unix::fs::symlink(target, path)
+                    .with_context(|| format!("Failed to link {:?} to {}", path, target))?;
+            }
+        }
+
+        if !java_path.is_file() {
+            anyhow::bail!("Java runtime {} has no {:?}", component, java_path);
+        }
+        println!("Java runtime {} installed in {}", component, home.display());
+        Ok(java_path)
+    }
+
+    /// Installs the Mojang runtime a version asks for, unless an installed Java already fits it.
+    async fn ensure_java_runtime(&self, java_version: Option<&JavaVersionSpec>) {
+        if self.java_path.is_some() {
+            return;
+        }
+        let required = java_version.map_or(java::DEFAULT_JAVA_VERSION, |spec| spec.major_version);
+        if java::discover(&self.runtime_dir).iter().any(|java| java.major_version == required) {
+            return;
+        }
+        let component = java_version
+            .and_then(|spec| spec.component.as_deref())
+            .unwrap_or(java::LEGACY_RUNTIME_COMPONENT);
+        if self.offline || java::runtime_platform().is_none() {
+            println!("Java {} is not installed, install it or launch with --runtime", required);
+            return;
         }
+        // The version itself is installed by now, a missing runtime only needs another try
+        if let Err(e) = self.install_java_runtime(component).await {
+            eprintln!("Failed to install Java runtime {}: {:#}", component, e);
+            eprintln!("Retry with: mclc java install {}", component);
+        }
+    }
 
-        // Now collect library and asset tasks
+    /// Downloads files that belong to no version (installer libraries, modpack files), skipping those already in place.
+    pub async fn download_files(&self, mut tasks: Vec<DownloadTask>) -> anyhow::Result<()> {
+        tasks.retain(|task| !Self::task_satisfied(task));
+        if tasks.is_empty() {
+            return Ok(());
+        }
+        if self.offline {
+            return Err(LauncherError::MissingWhileOffline(tasks.len()).into());
+        }
+        let failures = self.download_tasks(Client::new(), tasks).await;
+        if !failures.is_empty() {
+            Self::report_failures(&failures);
+            return Err(LauncherError::DownloadsFailed(failures).into());
+        }
+        Ok(())
+    }
+
+    /// Gives every library that only has a Maven `name` (and repository `url`) a `downloads.artifact`,
+    /// so the profile installs and launches like any other version JSON.
+    async fn complete_maven_libraries(&self, client: &Client, profile: &mut serde_json::Value) -> anyhow::Result<()> {
+        let Some(libraries) = profile.get_mut("libraries").and_then(|l| l.as_array_mut()) else {
+            return Ok(());
+        };
+        for library in libraries {
+            if library.get("downloads").is_some() {
+                continue;
+            }
+            let name = library["name"].as_str().context("Library without a name")?.to_string();
+            let path = maven::artifact_path(&name).with_context(|| format!("Invalid Maven coordinate {}", name))?;
+            let repository = library["url"].as_str().unwrap_or(maven::MOJANG_LIBRARIES_URL);
+            let url = format!("{}/{}", repository.trim_end_matches('/'), path);
+
+            // Repositories publish the checksum next to the artifact when the profile lacks it
+            let sha1 = match library["sha1"].as_str() {
+                Some(sha1) => Some(sha1.to_string()),
+                None => self
+                    .fetch_text(client, &format!("{}.sha1", url))
+                    .await
+                    .ok()
+                    .and_then(|text| text.split_whitespace().next().map(str::to_string))
+                    .filter(|sha1| sha1.len() == 40 && sha1.chars().all(|c| c.is_ascii_hexdigit())),
+            };
+            // Without one the library keeps its Maven name and is downloaded unverified
+            let Some(sha1) = sha1 else {
+                println!("No checksum published for {}, it will not be verified", name);
+                continue;
+            };
+            let size = library["size"].as_u64();
+            library["downloads"] = serde_json::json!({
+                "artifact": { "path": path, "url": url, "sha1": sha1, "size": size }
+            });
+        }
+        Ok(())
+    }
+
+    /// Checks every file of an installed version against its saved JSON.
+    /// Returns whether the version is intact once done (after repairing, if requested).
+    pub async fn verify_version(&self, version_id: &str, repair: bool) -> anyhow::Result<bool> {
+        let version_dir = self.versions_dir.join(version_id);
+        let version_details = version::load_version(&self.versions_dir, version_id)
+            .with_context(|| format!("Version {} is not installed", version_id))?;
+
+        println!("Verifying Minecraft version: {}", version_id);
         let client = Client::new();
-        let version_natives_dir = version_dir.join("natives");
-        fs::create_dir_all(&version_natives_dir)?;
-        self.collect_library_download_tasks(&version_details, version_id, &version_natives_dir, &mut tasks, &mut extraction_tasks);
 
-        // ARM64 natives for Linux
-        if std::env::consts::OS == "linux" && std::env::consts::ARCH == "aarch64" {
-            self.collect_lwjgl_arm64_download_tasks(&version_details, version_id, &version_natives_dir, &mut tasks, &mut extraction_tasks);
+        // Asset objects can only be checked once the index itself is intact
+        if let Some(index_task) = self.asset_index_task(&version_details)
+            && !Self::task_satisfied(&index_task) {
+            if repair && !self.offline {
+                println!("Asset index {} is missing or corrupt, re-downloading it first", index_task.path.display());
+                Self::download_file(&client, &index_task, &self.sources, self.max_retries)
+                    .await
+                    .with_context(|| format!("Failed to download {}", index_task.task_type))?;
+            } else {
+                println!("Asset index {} is missing or corrupt, asset objects cannot be checked", index_task.path.display());
+            }
         }
 
-        // Assets (now index should exist)
-        if let Some(ref index_path) = asset_index_path {
-            if index_path.exists() {
-                self.collect_asset_download_tasks(index_path, &mut tasks);
+        let version_natives_dir = version_dir.join("natives");
+        let mut extraction_tasks = Vec::new();
+        let expected = self.collect_version_files(&version_details, &version_natives_dir, &mut extraction_tasks);
+        let total = expected.len();
+
+        let mut missing = Vec::new();
+        let mut corrupt = Vec::new();
+        for task in expected {
+            if !task.path.exists() {
+                missing.push(task);
+            } else if !Self::task_satisfied(&task) {
+                corrupt.push(task);
             }
         }
+        let extra = Self::find_extra_files(&version_dir, version_id)?;
 
-        if tasks.is_empty() && extraction_tasks.is_empty() {
-            println!("All files already downloaded for version {}!", version_id);
-            // Still need to extract natives if they exist
-            for (jar_path, extract_dir) in &extraction_tasks {
-                if jar_path.exists() {
-                    self.extract_lwjgl3_native_library(jar_path, extract_dir)?;
+        for (label, tasks) in [("Missing", &missing), ("Corrupt", &corrupt)] {
+            if !tasks.is_empty() {
+                println!("{} ({}):", label, tasks.len());
+                for task in tasks {
+                    println!("  [{}] {}", task.task_type, task.path.display());
                 }
             }
-            return Ok(());
+        }
+        if !extra.is_empty() {
+            println!("Extra ({}):", extra.len());
+            for path in &extra {
+                println!("  {}", path.display());
+            }
         }
 
+        if missing.is_empty() && corrupt.is_empty() {
+            println!("All {} files of version {} are intact", total, version_id);
+            return Ok(true);
+        }
+
+        if !repair {
+            println!("Run 'mclc verify {} --repair' to re-download the missing and corrupt files", version_id);
+            return Ok(false);
+        }
+        if self.offline {
+            println!("Cannot repair version {} while offline", version_id);
+            return Ok(false);
+        }
+
+        let mut bad = missing;
+        bad.extend(corrupt);
+        println!("Repairing {} file(s)...", bad.len());
+        let failures = self.download_tasks(client, bad).await;
+        Self::report_failures(&failures);
+
+        fs::create_dir_all(&version_natives_dir)?;
+        for (jar_path, extract_dir) in extraction_tasks {
+            if jar_path.exists() {
+                self.extract_lwjgl3_native_library(&jar_path, &extract_dir)?;
+            }
+        }
+
+        if failures.is_empty() {
+            println!("Version {} repaired successfully!", version_id);
+        }
+        Ok(failures.is_empty())
+    }
+
+    /// Files in the version directory that the version JSON does not account for.
+    fn find_extra_files(version_dir: &Path, version_id: &str) -> anyhow::Result<Vec<std::path::PathBuf>> {
+        let known = [
+            format!("{}.json", version_id),
+            format!("{}.jar", version_id),
+            "natives".to_string(),
+        ];
+        let mut extra = Vec::new();
+        for entry in fs::read_dir(version_dir)? {
+            let entry = entry?;
+            if !known.iter().any(|name| entry.file_name() == name.as_str()) {
+                extra.push(entry.path());
+            }
+        }
+        extra.sort();
+        Ok(extra)
+    }
+
+    async fn download_tasks(
+        &self,
+        client: Client,
+        tasks: Vec<DownloadTask>,
+    ) -> Vec<FailedDownload> {
+        // Overlapping library lists and assets sharing a hash yield the same path twice,
+        // and two downloads must never share one `.part` file
+        let mut seen_paths = HashSet::new();
+        let tasks: Vec<DownloadTask> = tasks.into_iter().filter(|task| seen_paths.insert(task.path.clone())).collect();
+
         // Create progress bar
         let pb = Arc::new(ProgressBar::new(tasks.len() as u64));
         pb.set_style(ProgressStyle::default_bar()
@@ -160,128 +698,317 @@ impl Installer {
         let client = Arc::new(client);
         let counter = Arc::new(AtomicU64::new(0));
         let pb_clone = Arc::clone(&pb);
+        let max_retries = self.max_retries;
+        let sources = Arc::new(self.sources.clone());
 
         let download_results = stream::iter(tasks)
             .map(|task| {
                 let client = Arc::clone(&client);
                 let counter = Arc::clone(&counter);
                 let pb = Arc::clone(&pb_clone);
-                tokio::spawn(async move {
-                    let result = Self::download_file(&client, &task.url, &task.path).await;
+                let sources = Arc::clone(&sources);
+                let spawned_task = task.clone();
+                let handle = tokio::spawn(async move {
+                    let result = Self::download_file(&client, &spawned_task, &sources, max_retries).await;
                     if result.is_ok() {
                         let downloaded = counter.fetch_add(1, Ordering::SeqCst) + 1;
                         pb.set_position(downloaded);
                     }
+                    result
+                });
+                async move {
+                    // A panicked or cancelled download still counts as a failed file
+                    let result = handle.await.unwrap_or_else(|error| Err(anyhow::anyhow!("download task did not finish: {}", error)));
                     (task, result)
-                })
+                }
             })
             .buffer_unordered(MAX_CONCURRENT_DOWNLOADS)
             .collect::<Vec<_>>()
             .await;
 
         // Check for errors
-        for result in download_results {
-            if let Ok((task, download_result)) = result {
-                if let Err(e) = download_result {
-                    pb.println(format!("Failed to download {} ({}): {}", task.task_type, task.url, e));
-                }
+        let mut failures = Vec::new();
+        for (task, download_result) in download_results {
+            if let Err(error) = download_result {
+                failures.push(FailedDownload { task, error });
             }
         }
 
         pb.finish_with_message(format!("Downloaded {} files", counter.load(Ordering::SeqCst)));
+        failures
+    }
 
-        // Extract native libraries
-        for (jar_path, extract_dir) in extraction_tasks {
-            if jar_path.exists() {
-                self.extract_lwjgl3_native_library(&jar_path, &extract_dir)?;
+    fn report_failures(failures: &[FailedDownload]) {
+        if !failures.is_empty() {
+            eprintln!("{} file(s) could not be downloaded:", failures.len());
+            for FailedDownload { task, error } in failures {
+                eprintln!("  [{}] {}\n    from {}\n    {:#}", task.task_type, task.path.display(), task.url, error);
+            }
+        }
+    }
+
+    /// Fetches a document, falling back to the next source when one fails.
+    async fn fetch_text(&self, client: &Client, url: &str) -> anyhow::Result<String> {
+        let mut last_error = None;
+        for source in &self.sources {
+            let source_url = source.rewrite(url);
+            let result = async {
+                client.get(&source_url).send().await?.error_for_status()?.text().await
+            }
+            .await;
+            match result {
+                Ok(text) => return Ok(text),
+                Err(e) => {
+                    eprintln!("Failed to fetch {} from {} source: {}", url, source, e);
+                    last_error = Some(e);
+                }
             }
         }
+        match last_error {
+            Some(e) => Err(anyhow::Error::new(e).context(format!("Failed to fetch {}", url))),
+            None => anyhow::bail!("No download source configured"),
+        }
+    }
 
-        // Save version JSON
-        let version_json_path = version_dir.join(format!("{}.json", version_id));
-        let version_json_content = serde_json::to_string_pretty(&version_details)?;
-        fs::write(&version_json_path, version_json_content)?;
+    async fn fetch_json<T: DeserializeOwned>(&self, client: &Client, url: &str) -> anyhow::Result<T> {
+        let text = self.fetch_text(client, url).await?;
+        serde_json::from_str(&text).with_context(|| format!("Failed to parse {}", url))
+    }
 
-        println!("Version {} installed successfully!", version_id);
+    /// Whether any download source answers at all, used to detect that we are offline.
+    pub async fn network_available(&self) -> bool {
+        let client = Client::new();
+        for source in &self.sources {
+            let request = client.head(source.rewrite(VERSION_MANIFEST_URL)).timeout(CONNECTIVITY_TIMEOUT);
+            if request.send().await.is_ok() {
+                return true;
+            }
+        }
+        false
+    }
+
+    /// Fetches the version manifest, revalidating the cached copy with ETag/Last-Modified.
+    /// When offline or no source can be reached the cached copy is used as is.
+    async fn fetch_manifest(&self, client: &Client) -> anyhow::Result<VersionManifest> {
+        let cache_path = self.cache_dir.join(MANIFEST_CACHE_FILE);
+        let meta_path = self.cache_dir.join(MANIFEST_CACHE_META_FILE);
+        let cached = fs::read(&cache_path).ok();
+
+        if self.offline {
+            let bytes = cached.context("No cached version manifest, it has to be downloaded once while online")?;
+            return serde_json::from_slice(&bytes).with_context(|| format!("Failed to parse {:?}", cache_path));
+        }
+        let meta: ManifestCacheMeta = match &cached {
+            Some(_) => fs::read_to_string(&meta_path)
+                .ok()
+                .and_then(|json| serde_json::from_str(&json).ok())
+                .unwrap_or_default(),
+            None => ManifestCacheMeta::default(),
+        };
+
+        for source in &self.sources {
+            let mut request = client.get(source.rewrite(VERSION_MANIFEST_URL));
+            if let Some(etag) = &meta.etag {
+                request = request.header(reqwest::header::IF_NONE_MATCH, etag);
+            }
+            if let Some(last_modified) = &meta.last_modified {
+                request = request.header(reqwest::header::IF_MODIFIED_SINCE, last_modified);
+            }
+
+            let result = async {
+                let response = request.send().await?.error_for_status()?;
+                if response.status() == reqwest::StatusCode::NOT_MODIFIED {
+                    return Ok(None);
+                }
+                let header = |name: reqwest::header::HeaderName| {
+                    response.headers().get(name).and_then(|v| v.to_str().ok()).map(str::to_string)
+                };
+                let meta = ManifestCacheMeta {
+                    etag: header(reqwest::header::ETAG),
+                    last_modified: header(reqwest::header::LAST_MODIFIED),
+                };
+                Ok::<_, reqwest::Error>(Some((response.bytes().await?, meta)))
+            }
+            .await;
+
+            match result {
+                Ok(None) => {
+                    if let Some(bytes) = &cached {
+                        return serde_json::from_slice(bytes)
+                            .with_context(|| format!("Failed to parse {:?}", cache_path));
+                    }
+                }
+                Ok(Some((bytes, meta))) => match serde_json::from_slice::<VersionManifest>(&bytes) {
+                    Ok(manifest) => {
+                        self.save_manifest_cache(&bytes, &meta)?;
+                        return Ok(manifest);
+                    }
+                    Err(e) => eprintln!("Invalid version manifest from {} source: {}", source, e),
+                },
+                Err(e) => eprintln!("Failed to fetch {} from {} source: {}", VERSION_MANIFEST_URL, source, e),
+            }
+        }
+
+        match cached {
+            Some(bytes) => {
+                println!("Could not reach any download source, using the cached version manifest");
+                serde_json::from_slice(&bytes).with_context(|| format!("Failed to parse {:?}", cache_path))
+            }
+            None => anyhow::bail!("Failed to fetch the version manifest and no cached copy is available"),
+        }
+    }
+
+    fn save_manifest_cache(&self, bytes: &[u8], meta: &ManifestCacheMeta) -> anyhow::Result<()> {
+        fs::create_dir_all(&self.cache_dir)
+            .with_context(|| format!("Failed to create directory {:?}", self.cache_dir))?;
+        let cache_path = self.cache_dir.join(MANIFEST_CACHE_FILE);
+        let part = part_path(&cache_path);
+        fs::write(&part, bytes)?;
+        fs::rename(&part, &cache_path)?;
+        fs::write(self.cache_dir.join(MANIFEST_CACHE_META_FILE), serde_json::to_string_pretty(meta)?)?;
         Ok(())
     }
 
-    async fn fetch_version_details(
+    /// Returns the raw version JSON, checked against the SHA-1 from the manifest.
+    /// A saved copy at `saved_path` that still matches is reused without going to the network.
+    async fn fetch_version_json(
         &self,
         client: &Client,
-        version_id: &str,
-    ) -> anyhow::Result<(VersionInfo, VersionDetails)> {
-        let manifest: VersionManifest = client.get(VERSION_MANIFEST_URL).send().await?.json().await?;
-        let version_info = manifest
-            .versions
-            .iter()
-            .find(|v| v.id == version_id)
-            .ok_or_else(|| anyhow::anyhow!("Version {} not found", version_id))?;
+        version_info: &VersionInfo,
+        saved_path: &Path,
+    ) -> anyhow::Result<Vec<u8>> {
+        if Self::file_matches(saved_path, Some(&version_info.sha1), None) {
+            return Ok(fs::read(saved_path)?);
+        }
 
-        let version_details: VersionDetails = client.get(&version_info.url).send().await?.json().await?;
-        Ok((version_info.clone(), version_details))
+        let mut last_error = None;
+        for source in &self.sources {
+            let result = async {
+                let bytes = client
+                    .get(source.rewrite(&version_info.url))
+                    .send()
+                    .await?
+                    .error_for_status()?
+                    .bytes()
+                    .await?;
+                let actual = format!("{:x}", Sha1::digest(&bytes));
+                if !actual.eq_ignore_ascii_case(&version_info.sha1) {
+                    return Err(LauncherError::ChecksumMismatch {
+                        path: saved_path.to_path_buf(),
+                        expected: version_info.sha1.clone(),
+                        actual,
+                    }
+                    .into());
+                }
+                Ok::<_, anyhow::Error>(bytes.to_vec())
+            }
+            .await;
+            match result {
+                Ok(bytes) => return Ok(bytes),
+                Err(e) => {
+                    eprintln!("Failed to fetch {} from {} source: {:#}", version_info.url, source, e);
+                    last_error = Some(e);
+                }
+            }
+        }
+        match last_error {
+            Some(e) => Err(e.context(format!("Failed to fetch version JSON of {}", version_info.id))),
+            None => anyhow::bail!("No download source configured"),
+        }
     }
 
-    fn collect_library_download_tasks(
+    fn asset_index_task(&self, version_details: &VersionDetails) -> Option<DownloadTask> {
+        let asset_index = version_details.asset_index.as_ref()?;
+        Some(DownloadTask::new(
+            asset_index.url.clone(),
+            self.assets_indexes_dir.join(format!("{}.json", asset_index.id)),
+            "index",
+            Some(asset_index.sha1.clone()),
+            Some(asset_index.size),
+        ))
+    }
+
+    /// Every file a version needs: client JAR, asset index, rule-matching libraries and natives,
+    /// and the asset objects listed in the index when it is present locally.
+    fn collect_version_files(
         &self,
         version_details: &VersionDetails,
-        _version_id: &str,
+        version_natives_dir: &Path,
+        extraction_tasks: &mut Vec<(std::path::PathBuf, std::path::PathBuf)>,
+    ) -> Vec<DownloadTask> {
+        let mut tasks = Vec::new();
+
+        // Client JAR
+        if let Some(downloads) = &version_details.downloads {
+            tasks.push(DownloadTask::new(
+                downloads.client.url.clone(),
+                self.versions_dir.join(version_details.jar_id()).join(format!("{}.jar", version_details.jar_id())),
+                "client",
+                Some(downloads.client.sha1.clone()),
+                Some(downloads.client.size),
+            ));
+        }
+
+        self.collect_library_download_tasks(&version_details.libraries, version_natives_dir, &mut tasks, extraction_tasks);
+
+        if let Some(index_task) = self.asset_index_task(version_details) {
+            if index_task.path.exists() {
+                self.collect_asset_download_tasks(&index_task.path, &mut tasks);
+            }
+            tasks.push(index_task);
+        }
+
+        // Log4j config for `launch --wait`
+        if let Some(config) = version_details.logging.as_ref().and_then(|logging| logging.client.as_ref()) {
+            tasks.push(DownloadTask::new(config.file.url.clone(), self.log_configs_dir.join(&config.file.id), "log config", Some(config.file.sha1.clone()), Some(config.file.size)));
+        }
+
+        tasks
+    }
+
+    fn collect_library_download_tasks(
+        &self,
+        libraries: &[Library],
         version_natives_dir: &Path,
         tasks: &mut Vec<DownloadTask>,
         extraction_tasks: &mut Vec<(std::path::PathBuf, std::path::PathBuf)>,
     ) {
-        for library in &version_details.libraries {
-            let Some(downloads) = &library.downloads else {
-                continue;
-            };
+        let rules = RuleContext::current(Features::default());
 
-            if let Some(artifact) = &downloads.artifact {
-                let library_path = self.libraries_dir.join(&artifact.path);
-                if !library_path.exists() {
-                    if let Some(parent) = library_path.parent() {
-                        let _ = fs::create_dir_all(parent);
-                    }
-                    tasks.push(DownloadTask {
-                        url: artifact.url.clone(),
-                        path: library_path,
-                        task_type: "library".to_string(),
-                    });
-                }
+        for library in libraries {
+            if !rules.library_allowed(library) {
+                continue;
             }
 
-            if let Some(classifiers) = &downloads.classifiers {
-                if let Some(artifact) = self.get_native_artifact(classifiers) {
-                    let native_path = self.libraries_dir.join(&artifact.path);
-                    if !native_path.exists() {
-                        if let Some(parent) = native_path.parent() {
-                            let _ = fs::create_dir_all(parent);
-                        }
-                        tasks.push(DownloadTask {
-                            url: artifact.url.clone(),
-                            path: native_path.clone(),
-                            task_type: "native".to_string(),
-                        });
+            match &library.downloads {
+                // Artifacts without a URL are bundled with or generated by a loader installer
+                Some(downloads) => {
+                    if let Some(artifact) = &downloads.artifact
+                        && !artifact.url.is_empty() {
+                        tasks.push(DownloadTask::new(artifact.url.clone(), self.libraries_dir.join(&artifact.path), "library", Some(artifact.sha1.clone()), artifact.size));
                     }
-                    extraction_tasks.push((native_path, version_natives_dir.to_path_buf()));
                 }
-
-                // Other natives
-                for (classifier_name, artifact) in &classifiers.other {
-                    if classifier_name.contains("natives-") {
-                        let native_path = self.libraries_dir.join(&artifact.path);
-                        if !native_path.exists() {
-                            if let Some(parent) = native_path.parent() {
-                                let _ = fs::create_dir_all(parent);
-                            }
-                            tasks.push(DownloadTask {
-                                url: artifact.url.clone(),
-                                path: native_path.clone(),
-                                task_type: "native".to_string(),
-                            });
-                        }
-                        extraction_tasks.push((native_path, version_natives_dir.to_path_buf()));
+                // Only a Maven coordinate and maybe a repository, as in Fabric, Quilt and older Forge profiles
+                None => match maven::artifact_path(&library.name) {
+                    Some(path) => {
+                        let repository = library.url.as_deref().unwrap_or(maven::MOJANG_LIBRARIES_URL);
+                        tasks.push(DownloadTask::new(format!("{}/{}", repository.trim_end_matches('/'), path), self.libraries_dir.join(&path), "library", library.sha1.clone(), library.size));
                     }
-                }
+                    None => eprintln!("Skipping library with invalid Maven coordinate: {}", library.name),
+                },
+            }
+
+            if let Some(artifact) = rules.native_artifact(library) {
+                let native_path = self.libraries_dir.join(&artifact.path);
+                tasks.push(DownloadTask::new(artifact.url.clone(), native_path.clone(), "native", Some(artifact.sha1.clone()), artifact.size));
+                extraction_tasks.push((native_path, version_natives_dir.to_path_buf()));
+            } else if rules.lacks_arch_natives(library)
+                // LWJGL 3 on Linux ARM64 gets its natives from collect_lwjgl_arm64_download_tasks
+                && !(rules.os_name == "linux" && library.name.starts_with("org.lwjgl:")) {
+                eprintln!(
+                    "Warning: {} has no {} natives for {}, the game may fail to load them",
+                    library.name, rules.os_arch, rules.os_name
+                );
             }
         }
     }
@@ -344,92 +1071,172 @@ impl Installer {
                 module_artifact_id, module_version, classifier
             ));
 
-            tasks.push(DownloadTask {
-                url,
-                path: temp_file_path.clone(),
-                task_type: "arm64-native".to_string(),
-            });
+            tasks.push(DownloadTask::new(url, temp_file_path.clone(), "arm64-native", None, None));
             extraction_tasks.push((temp_file_path, version_natives_dir.to_path_buf()));
         }
     }
 
     fn collect_asset_download_tasks(&self, asset_index_path: &Path, tasks: &mut Vec<DownloadTask>) {
-        if let Ok(asset_index_content) = fs::read_to_string(asset_index_path) {
-            if let Ok(assets_index) = serde_json::from_str::<AssetsIndex>(&asset_index_content) {
-                for (_name, asset_object) in &assets_index.objects {
-                    let hash = &asset_object.hash;
-                    let first_two = &hash[..2];
-                    let asset_path = self.assets_objects_dir.join(first_two).join(hash);
-
-                    if !asset_path.exists() {
-                        if let Some(parent) = asset_path.parent() {
-                            let _ = fs::create_dir_all(parent);
-                        }
-                        let asset_url = format!("{}/{}/{}", ASSET_BASE_URL, first_two, hash);
-                        tasks.push(DownloadTask {
-                            url: asset_url,
-                            path: asset_path,
-                            task_type: "asset".to_string(),
-                        });
-                    }
+        if let Ok(asset_index_content) = fs::read_to_string(asset_index_path)
+            && let Ok(assets_index) = serde_json::from_str::<AssetsIndex>(&asset_index_content) {
+            for asset_object in assets_index.objects.values() {
+                let hash = &asset_object.hash;
+                let first_two = &hash[..2];
+                let asset_path = self.assets_objects_dir.join(first_two).join(hash);
+                let asset_url = format!("{}/{}/{}", ASSET_BASE_URL, first_two, hash);
+                tasks.push(DownloadTask::new(asset_url, asset_path, "asset", Some(hash.clone()), Some(asset_object.size)));
+            }
+        }
+    }
+
+    /// Downloads a task from each source in turn until one succeeds.
+    async fn download_file(
+        client: &Client,
+        task: &DownloadTask,
+        sources: &[DownloadSource],
+        max_retries: u32,
+    ) -> anyhow::Result<()> {
+        let mut last_error = None;
+        for source in sources {
+            let source_task = DownloadTask {
+                url: source.rewrite(&task.url),
+                ..task.clone()
+            };
+            match Self::download_with_retries(client, &source_task, max_retries).await {
+                Ok(()) => return Ok(()),
+                Err(e) => last_error = Some(e.context(format!("{} source failed", source))),
+            }
+        }
+        Err(last_error.unwrap_or_else(|| anyhow::anyhow!("No download source configured")))
+    }
+
+    /// Downloads a task, retrying transient failures with exponential backoff and jitter.
+    async fn download_with_retries(client: &Client, task: &DownloadTask, max_retries: u32) -> anyhow::Result<()> {
+        let mut attempt = 0;
+        loop {
+            match Self::fetch_and_verify(client, task).await {
+                Ok(()) => return Ok(()),
+                Err(e) if attempt < max_retries && is_retryable(&e) => {
+                    let backoff = RETRY_BASE_DELAY.saturating_mul(1 << attempt.min(16)).min(RETRY_MAX_DELAY);
+                    let jitter = Duration::from_millis(fastrand::u64(0..=backoff.as_millis() as u64 / 2));
+                    tokio::time::sleep(backoff + jitter).await;
+                    attempt += 1;
+                }
+                Err(e) => {
+                    return Err(e.context(format!("gave up after {} attempt(s)", attempt + 1)));
                 }
             }
         }
     }
 
-    async fn download_file(client: &Client, url: &str, path: &Path) -> anyhow::Result<()> {
-        if path.exists() {
-            return Ok(());
+    /// Streams the task into `<path>.part`, resuming an earlier partial download with a Range
+    /// request when possible, and only moves it into place once it passes verification.
+    async fn fetch_and_verify(client: &Client, task: &DownloadTask) -> anyhow::Result<()> {
+        if let Some(parent) = task.path.parent() {
+            fs::create_dir_all(parent)
+                .with_context(|| format!("Failed to create directory {:?}", parent))?;
         }
 
-        let response = client.get(url).send().await?;
+        let part_path = part_path(&task.path);
+        let (response, resume_from) = loop {
+            let mut resume_from = fs::metadata(&part_path).map(|m| m.len()).unwrap_or(0);
+            if let Some(size) = task.size
+                && resume_from >= size {
+                // Already too long to be a prefix of the file, start over
+                fs::remove_file(&part_path)?;
+                resume_from = 0;
+            }
+
+            let mut request = client.get(&task.url);
+            if resume_from > 0 {
+                request = request.header(reqwest::header::RANGE, format!("bytes={}-", resume_from));
+            }
+            let response = request.send().await?;
+
+            if resume_from > 0 && response.status() == reqwest::StatusCode::RANGE_NOT_SATISFIABLE {
+                fs::remove_file(&part_path)?;
+                continue;
+            }
+            break (response.error_for_status()?, resume_from);
+        };
+
+        {
+            // Servers without range support answer with the whole file, so only append on 206
+            let mut file = if resume_from > 0 && response.status() == reqwest::StatusCode::PARTIAL_CONTENT {
+                fs::OpenOptions::new().append(true).open(&part_path)
+            } else {
+                File::create(&part_path)
+            }
+            .with_context(|| format!("Failed to create {:?}", part_path))?;
+            let mut stream = response.bytes_stream();
+
+            while let Some(chunk) = stream.next().await {
+                let chunk = chunk.context("Failed to read chunk")?;
+                file.write_all(&chunk).context("Failed to write chunk")?;
+            }
+        }
 
-        let mut file = File::create(path).context("Failed to create file")?;
-        let mut stream = response.bytes_stream();
+        if let Some(expected) = task.size {
+            let actual = fs::metadata(&part_path)?.len();
+            if actual != expected {
+                let _ = fs::remove_file(&part_path);
+                return Err(LauncherError::SizeMismatch {
+                    path: task.path.clone(),
+                    expected,
+                    actual,
+                }
+                .into());
+            }
+        }
 
-        while let Some(chunk) = stream.next().await {
-            let chunk = chunk.context("Failed to read chunk")?;
-            file.write_all(&chunk).context("Failed to write chunk")?;
+        if let Some(expected) = &task.sha1 {
+            let actual = Self::sha1_file(&part_path)?;
+            if !actual.eq_ignore_ascii_case(expected) {
+                let _ = fs::remove_file(&part_path);
+                return Err(LauncherError::ChecksumMismatch {
+                    path: task.path.clone(),
+                    expected: expected.clone(),
+                    actual,
+                }
+                .into());
+            }
         }
 
+        fs::rename(&part_path, &task.path)
+            .with_context(|| format!("Failed to move {:?} into place", part_path))?;
         Ok(())
     }
 
-    fn get_native_artifact<'a>(&self, classifiers: &'a Classifiers) -> Option<&'a Artifact> {
-        let os_name = match std::env::consts::OS {
-            "windows" => "windows",
-            "linux" => "linux",
-            "macos" => "macos",
-            _ => return None,
-        };
-        let arch_name = match std::env::consts::ARCH {
-            "aarch64" => "aarch64",
-            "x86_64" => "x64",
-            _ => std::env::consts::ARCH,
-        };
+    fn task_satisfied(task: &DownloadTask) -> bool {
+        Self::file_matches(&task.path, task.sha1.as_deref(), task.size)
+    }
 
-        let specific_key = format!("natives-{}-{}", os_name, arch_name);
-        if let Some(artifact) = classifiers.other.get(&specific_key) {
-            return Some(artifact);
+    /// Whether `path` exists and matches the expected size and SHA-1 (when known).
+    fn file_matches(path: &Path, sha1: Option<&str>, size: Option<u64>) -> bool {
+        let Ok(metadata) = fs::metadata(path) else {
+            return false;
+        };
+        if let Some(size) = size
+            && metadata.len() != size {
+            return false;
         }
-
-        match std::env::consts::OS {
-            "windows" => classifiers
-                .natives_windows
-                .as_ref()
-                .or_else(|| classifiers.other.get("natives-windows")),
-            "linux" => classifiers
-                .natives_linux
-                .as_ref()
-                .or_else(|| classifiers.other.get("natives-linux")),
-            "macos" => classifiers
-                .natives_macos
-                .as_ref()
-                .or_else(|| classifiers.other.get("natives-macos")),
-            _ => None,
+        match sha1 {
+            Some(expected) => Self::sha1_file(path)
+                .map(|actual| actual.eq_ignore_ascii_case(expected))
+                .unwrap_or(false),
+            None => true,
         }
     }
 
+    pub fn sha1_file(path: &Path) -> anyhow::Result<String> {
+        let mut file = File::open(path)
+            .with_context(|| format!("Failed to open {:?} for hashing", path))?;
+        let mut hasher = Sha1::new();
+        std::io::copy(&mut file, &mut hasher)
+            .with_context(|| format!("Failed to hash {:?}", path))?;
+        Ok(format!("{:x}", hasher.finalize()))
+    }
+
     fn extract_lwjgl3_native_library(&self, jar_path: &Path, extract_dir: &Path) -> anyhow::Result<()> {
         let file = std::fs::File::open(jar_path)
             .with_context(|| format!("Failed to open native JAR file: {:?}", jar_path))?;
@@ -444,12 +1251,11 @@ impl Installer {
             let file_name = file.name().to_lowercase();
 
             if file_name.ends_with(".dll") || file_name.ends_with(".so") || file_name.ends_with(".dylib") {
-                if let Some(p) = outpath.parent() {
-                    if !p.exists() {
-                        fs::create_dir_all(p).with_context(|| {
-                            format!("Failed to create directory for native file: {:?}", p)
-                        })?;
-                    }
+                if let Some(p) = outpath.parent()
+                    && !p.exists() {
+                    fs::create_dir_all(p).with_context(|| {
+                        format!("Failed to create directory for native file: {:?}", p)
+                    })?;
                 }
                 let mut outfile = std::fs::File::create(&outpath)
                     .with_context(|| format!("Failed to create output file for native: {:?}", outpath))?;
@@ -460,3 +1266,162 @@ impl Installer {
         Ok(())
     }
 }
+
+fn part_path(path: &Path) -> std::path::PathBuf {
+    let mut part = path.as_os_str().to_os_string();
+    part.push(".part");
+    std::path::PathBuf::from(part)
+}
+
+/// Network hiccups, server errors and corrupt transfers are worth another try; client errors and local I/O failures are not.
+fn is_retryable(e: &anyhow::Error) -> bool {
+    if let Some(error) = e.downcast_ref::<reqwest::Error>() {
+        return match error.status() {
+            Some(status) => {
+                status.is_server_error()
+                    || status == reqwest::StatusCode::REQUEST_TIMEOUT
+                    || status == reqwest::StatusCode::TOO_MANY_REQUESTS
+            }
+            None => true,
+        };
+    }
+    matches!(
+        e.downcast_ref::<LauncherError>(),
+        Some(LauncherError::SizeMismatch { .. } | LauncherError::ChecksumMismatch { .. })
+    )
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+
+    /// Serves `routes` over plain HTTP, answering 404 for anything else.
+    pub(crate) fn serve(listener: TcpListener, routes: HashMap<String, Vec<u8>>) {
+        let routes = Arc::new(routes);
+        tokio::spawn(async move {
+            while let Ok((mut stream, _)) = listener.accept().await {
+                let routes = routes.clone();
+                tokio::spawn(async move {
+                    let mut request = Vec::new();
+                    let mut buf = [0; 1024];
+                    while !request.windows(4).any(|w| w == b"\r\n\r\n") {
+                        match stream.read(&mut buf).await {
+                            Ok(0) | Err(_) => return,
+                            Ok(n) => request.extend_from_slice(&buf[..n]),
+                        }
+                    }
+                    let request = String::from_utf8_lossy(&request);
+                    let path = request.split_whitespace().nth(1).unwrap_or_default();
+                    let (status, body) = match routes.get(path) {
+                        Some(body) => ("200 OK", body.as_slice()),
+                        None => ("404 Not Found", &b""[..]),
+                    };
+                    let head = format!("HTTP/1.1 {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n", status, body.len());
+                    let _ = stream.write_all(head.as_bytes()).await;
+                    let _ = stream.write_all(body).await;
+                });
+            }
+        });
+    }
+
+    pub(crate) fn sha1_hex(bytes: &[u8]) -> String {
+        format!("{:x}", Sha1::digest(bytes))
+    }
+
+    /// Routes serving a version manifest with a bare 1.20.1 and its version JSON.
+    pub(crate) fn vanilla_routes() -> HashMap<String, Vec<u8>> {
+        let vanilla = br#"{"id": "1.20.1", "type": "release", "mainClass": "net.minecraft.client.main.Main"}"#.to_vec();
+        let manifest = serde_json::json!({
+            "latest": {"release": "1.20.1", "snapshot": "1.20.1"},
+            "versions": [{
+                "id": "1.20.1",
+                "type": "release",
+                "url": "https://piston-meta.mojang.com/v1/packages/x/1.20.1.json",
+                "time": "2023-06-12T13:25:51+00:00",
+                "releaseTime": "2023-06-12T13:25:51+00:00",
+                "sha1": sha1_hex(&vanilla)
+            }]
+        });
+        HashMap::from([
+            ("/mc/game/version_manifest_v2.json".to_string(), manifest.to_string().into_bytes()),
+            ("/v1/packages/x/1.20.1.json".to_string(), vanilla),
+        ])
+    }
+
+    /// An installer working in `dir` that downloads everything from the stub at `base_url`.
+    pub(crate) fn stub_installer(dir: &Path, base_url: &str) -> Installer {
+        Installer {
+            versions_dir: dir.join("versions"),
+            libraries_dir: dir.join("libraries"),
+            assets_objects_dir: dir.join("assets/objects"),
+            assets_indexes_dir: dir.join("assets/indexes"),
+            log_configs_dir: dir.join("assets/log_configs"),
+            max_retries: 0,
+            sources: vec![DownloadSource::Custom(format!("{}/{{path}}", base_url))],
+            cache_dir: dir.join("cache"),
+            runtime_dir: dir.join("runtime"),
+            offline: false,
+            fabric_meta_url: format!("{}/fabric", base_url),
+            quilt_meta_url: format!("{}/quilt", base_url),
+            // Leaves the Java runtime alone
+            java_path: Some(dir.join("java")),
+        }
+    }
+
+    #[tokio::test]
+    async fn install_loader_from_stub_meta() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let base_url = format!("http://127.0.0.1:{}", listener.local_addr().unwrap().port());
+        let maven = format!("{}/maven/", base_url);
+
+        let loaders = br#"[{"loader": {"version": "0.16.0-beta", "stable": false}}, {"loader": {"version": "0.15.11", "stable": true}}]"#;
+        let profile = serde_json::json!({
+            "id": "fabric-loader-0.15.11-1.20.1",
+            "inheritsFrom": "1.20.1",
+            "type": "release",
+            "mainClass": "net.fabricmc.loader.impl.launch.knot.KnotClient",
+            "libraries": [
+                {"name": "net.fabricmc:fabric-loader:0.15.11", "url": maven},
+                {"name": "net.fabricmc:intermediary:1.20.1", "url": maven},
+                {"name": "org.ow2.asm:asm:9.6", "url": maven}
+            ]
+        });
+        let loader_jar = b"fabric-loader".to_vec();
+        let mut routes = vanilla_routes();
+        routes.extend([
+            ("/fabric/v2/versions/loader/1.20.1".to_string(), loaders.to_vec()),
+            ("/fabric/v2/versions/loader/1.20.1/0.15.11/profile/json".to_string(), profile.to_string().into_bytes()),
+            ("/maven/net/fabricmc/fabric-loader/0.15.11/fabric-loader-0.15.11.jar.sha1".to_string(), sha1_hex(&loader_jar).into_bytes()),
+            ("/maven/net/fabricmc/fabric-loader/0.15.11/fabric-loader-0.15.11.jar".to_string(), loader_jar),
+            // intermediary has no sidecar at all, asm an empty one
+            ("/maven/net/fabricmc/intermediary/1.20.1/intermediary-1.20.1.jar".to_string(), b"intermediary".to_vec()),
+            ("/maven/org/ow2/asm/asm/9.6/asm-9.6.jar.sha1".to_string(), Vec::new()),
+            ("/maven/org/ow2/asm/asm/9.6/asm-9.6.jar".to_string(), b"asm".to_vec()),
+        ]);
+        serve(listener, routes);
+
+        let minecraft_dir = tempfile::tempdir().unwrap();
+        let dir = minecraft_dir.path();
+        let installer = stub_installer(dir, &base_url);
+        let spec = LoaderSpec { loader: Loader::Fabric, version: None };
+        let profile_id = installer.install_loader("1.20.1", &spec).await.unwrap();
+        assert_eq!(profile_id, "fabric-loader-0.15.11-1.20.1");
+
+        let saved = fs::read(dir.join("versions").join(&profile_id).join(format!("{}.json", profile_id))).unwrap();
+        let saved: serde_json::Value = serde_json::from_slice(&saved).unwrap();
+        let libraries = saved["libraries"].as_array().unwrap();
+        assert_eq!(libraries[0]["downloads"]["artifact"]["sha1"].as_str(), Some(sha1_hex(b"fabric-loader").as_str()));
+        assert!(libraries[1].get("downloads").is_none());
+        assert!(libraries[2].get("downloads").is_none());
+
+        for path in [
+            "net/fabricmc/fabric-loader/0.15.11/fabric-loader-0.15.11.jar",
+            "net/fabricmc/intermediary/1.20.1/intermediary-1.20.1.jar",
+            "org/ow2/asm/asm/9.6/asm-9.6.jar",
+        ] {
+            assert!(dir.join("libraries").join(path).is_file(), "{} was not downloaded", path);
+        }
+    }
+}
@@ -0,0 +1,263 @@
+use crate::error::LauncherError;
+use crate::models::JavaVersionSpec;
+use crate::rules::normalize_arch;
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// Directory inside the Minecraft directory holding Java runtimes, shared with the official launcher.
+pub const RUNTIME_DIR: &str = "runtime";
+/// Mojang runtime for versions that predate `javaVersion.component`.
+pub const LEGACY_RUNTIME_COMPONENT: &str = "jre-legacy";
+
+const JAVA_EXECUTABLE: &str = if cfg!(target_os = "windows") { "java.exe" } else { "java" };
+
+/// A Java installation found on this machine.
+#[derive(Debug, Clone)]
+pub struct JavaInstallation {
+    /// The `java` executable
+    pub path: PathBuf,
+    /// Full version, e.g. `21.0.5` or `1.8.0_432`
+    pub version: String,
+    pub major_version: u32,
+    pub vendor: Option<String>,
+    pub arch: Option<String>,
+}
+
+/// Java assumed for versions that predate `javaVersion` in the version JSON.
+pub const DEFAULT_JAVA_VERSION: u32 = 8;
+
+/// Picks the installed Java matching `javaVersion` of a version, preferring the runtime named by its
+/// `component` and then one built for this machine's architecture. Without an exact match the lowest
+/// newer Java is used, with the same warning as [`check_java`].
+pub fn select_java(runtime_dir: &Path, java_version: Option<&JavaVersionSpec>) -> anyhow::Result<JavaInstallation> {
+    let required = java_version.map_or(DEFAULT_JAVA_VERSION, |spec| spec.major_version);
+    let component = java_version.and_then(|spec| spec.component.as_deref());
+    let host_arch = normalize_arch(std::env::consts::ARCH);
+    let preference = |java: &JavaInstallation| {
+        let from_component = component.is_some_and(|component| {
+            java.path.components().any(|part| part.as_os_str() == component)
+        });
+        (!from_component, java.arch.as_deref() != Some(host_arch))
+    };
+
+    let installations = discover(runtime_dir);
+    // min_by_key keeps the first of equal keys, so discovery order breaks ties
+    let java = installations
+        .iter()
+        .filter(|java| java.major_version == required)
+        .min_by_key(|java| preference(java))
+        .or_else(|| {
+            installations
+                .iter()
+                .filter(|java| java.major_version > required)
+                .min_by_key(|java| (java.major_version, preference(java)))
+        });
+    match java {
+        Some(java) => {
+            println!("Found Java {} at {:?}", java.version, java.path);
+            if java.major_version > required {
+                eprintln!(
+                    "Warning: {:?} is Java {}, but this version is made for Java {} and may not start",
+                    java.path, java.major_version, required
+                );
+            }
+            Ok(java.clone())
+        }
+        None => {
+            if !installations.is_empty() {
+                let mut found: Vec<u32> = installations.iter().map(|java| java.major_version).collect();
+                found.sort_unstable();
+                found.dedup();
+                let found: Vec<String> = found.iter().map(u32::to_string).collect();
+                eprintln!("Found Java {}, but not Java {} or newer", found.join(", "), required);
+            }
+            Err(LauncherError::JavaNotFound(required).into())
+        }
+    }
+}
+
+/// Checks a Java given explicitly against `javaVersion` of a version: an older Java cannot load
+/// the game, a newer one only gets a warning.
+pub fn check_java(java_path: &Path, java_version: Option<&JavaVersionSpec>) -> anyhow::Result<()> {
+    let required = java_version.map_or(DEFAULT_JAVA_VERSION, |spec| spec.major_version);
+    let Some(java) = probe(java_path) else {
+        eprintln!("Could not determine the version of {:?}, this version needs Java {}", java_path, required);
+        return Ok(());
+    };
+    if java.major_version < required {
+        return Err(LauncherError::JavaTooOld {
+            path: java_path.to_path_buf(),
+            found: java.major_version,
+            required,
+        }
+        .into());
+    }
+    if java.major_version > required {
+        eprintln!(
+            "Warning: {:?} is Java {}, but this version is made for Java {} and may not start",
+            java_path, java.major_version, required
+        );
+    }
+    Ok(())
+}
+
+/// Every Java installation that can be found, in the order they were searched:
+/// `JAVA_HOME`, `PATH`, the usual system and version manager directories, then the runtimes in `runtime_dir`.
+pub fn discover(runtime_dir: &Path) -> Vec<JavaInstallation> {
+    let mut seen = HashSet::new();
+    candidates(runtime_dir)
+        .into_iter()
+        .filter(|path| path.is_file())
+        .filter_map(|path| fs::canonicalize(&path).ok())
+        .filter(|path| seen.insert(path.clone()))
+        .filter_map(|path| probe(&path))
+        .collect()
+}
+
+/// Reads version, vendor and arch from the installation's `release` file, or asks the executable.
+pub fn probe(java_path: &Path) -> Option<JavaInstallation> {
+    probe_release_file(java_path).or_else(|| probe_executable(java_path))
+}
+
+fn candidates(runtime_dir: &Path) -> Vec<PathBuf> {
+    let mut candidates = Vec::new();
+    if let Some(java_home) = std::env::var_os("JAVA_HOME") {
+        candidates.push(Path::new(&java_home).join("bin").join(JAVA_EXECUTABLE));
+    }
+    if let Some(path) = std::env::var_os("PATH") {
+        candidates.extend(std::env::split_paths(&path).map(|dir| dir.join(JAVA_EXECUTABLE)));
+    }
+
+    let home = dirs::home_dir().unwrap_or_default();
+    let mut search_dirs = vec![
+        PathBuf::from("/usr/lib/jvm"),
+        PathBuf::from("/usr/java"),
+        PathBuf::from("/opt"),
+        std::env::var_os("SDKMAN_DIR").map(PathBuf::from).unwrap_or_else(|| home.join(".sdkman")).join("candidates/java"),
+        std::env::var_os("ASDF_DATA_DIR").map(PathBuf::from).unwrap_or_else(|| home.join(".asdf")).join("installs/java"),
+        home.join(".jdks"),
+    ];
+    if cfg!(target_os = "macos") {
+        search_dirs.push(PathBuf::from("/Library/Java/JavaVirtualMachines"));
+        search_dirs.push(home.join("Library/Java/JavaVirtualMachines"));
+    }
+    if cfg!(target_os = "windows") {
+        for program_files in ["ProgramFiles", "ProgramFiles(x86)"].iter().filter_map(std::env::var_os) {
+            let program_files = PathBuf::from(program_files);
+            for vendor in ["Java", "Eclipse Adoptium", "Microsoft", "Zulu", "BellSoft", "Amazon Corretto"] {
+                search_dirs.push(program_files.join(vendor));
+            }
+        }
+    }
+    for dir in &search_dirs {
+        find_java_homes(dir, 2, &mut candidates);
+    }
+    // Runtimes are laid out as <component>/<platform>/<component>/ by the official launcher
+    find_java_homes(runtime_dir, 4, &mut candidates);
+    candidates
+}
+
+/// Platform name Mojang publishes Java runtimes for this machine under.
+pub fn runtime_platform() -> Option<&'static str> {
+    match (std::env::consts::OS, std::env::consts::ARCH) {
+        ("linux", "x86_64") => Some("linux"),
+        ("linux", "x86") => Some("linux-i386"),
+        ("windows", "x86_64") => Some("windows-x64"),
+        ("windows", "x86") => Some("windows-x86"),
+        ("windows", "aarch64") => Some("windows-arm64"),
+        ("macos", "x86_64") => Some("mac-os"),
+        ("macos", "aarch64") => Some("mac-os-arm64"),
+        _ => None,
+    }
+}
+
+/// The `java` of a Mojang runtime unpacked into `home`; on macOS it sits inside a bundle.
+pub fn runtime_java(home: &Path) -> PathBuf {
+    if cfg!(target_os = "macos") {
+        home.join("jre.bundle/Contents/Home/bin").join(JAVA_EXECUTABLE)
+    } else {
+        home.join("bin").join(JAVA_EXECUTABLE)
+    }
+}
+
+/// Adds `bin/java` of every directory under `dir` (up to `depth` levels down) that has one.
+fn find_java_homes(dir: &Path, depth: usize, candidates: &mut Vec<PathBuf>) {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return;
+    };
+    let mut dirs: Vec<PathBuf> = entries
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|path| path.is_dir())
+        .collect();
+    dirs.sort();
+    for dir in dirs {
+        let java_bin = ["", "Contents/Home", "jre"]
+            .iter()
+            .map(|home| dir.join(home).join("bin").join(JAVA_EXECUTABLE))
+            .find(|java_bin| java_bin.is_file());
+        match java_bin {
+            Some(java_bin) => candidates.push(java_bin),
+            None if depth > 1 => find_java_homes(&dir, depth - 1, candidates),
+            None => {}
+        }
+    }
+}
+
+fn probe_release_file(java_path: &Path) -> Option<JavaInstallation> {
+    let home = java_path.parent()?.parent()?;
+    // A JDK 8 `jre/bin/java` has the `release` file next to `jre`
+    let release = [Some(home), home.parent()]
+        .into_iter()
+        .flatten()
+        .map(|dir| dir.join("release"))
+        .find_map(|path| fs::read_to_string(path).ok())?;
+    let value = |key: &str| {
+        release.lines().find_map(|line| {
+            let value = line.strip_prefix(key)?.strip_prefix('=')?;
+            Some(value.trim().trim_matches('"').to_string())
+        })
+    };
+    let version = value("JAVA_VERSION")?;
+    Some(JavaInstallation {
+        path: java_path.to_path_buf(),
+        major_version: major_version(&version)?,
+        version,
+        vendor: value("IMPLEMENTOR"),
+        arch: value("OS_ARCH").map(|arch| normalize_arch(&arch).to_string()),
+    })
+}
+
+fn probe_executable(java_path: &Path) -> Option<JavaInstallation> {
+    let output = Command::new(java_path)
+        .args(["-XshowSettings:properties", "-version"])
+        .output()
+        .ok()?;
+    // The settings are printed to stderr
+    let settings = String::from_utf8_lossy(&output.stderr);
+    let property = |key: &str| {
+        settings.lines().find_map(|line| {
+            let (name, value) = line.split_once('=')?;
+            (name.trim() == key).then(|| value.trim().to_string())
+        })
+    };
+    let version = property("java.version")?;
+    Some(JavaInstallation {
+        path: java_path.to_path_buf(),
+        major_version: major_version(&version)?,
+        version,
+        vendor: property("java.vendor"),
+        arch: property("os.arch").map(|arch| normalize_arch(&arch).to_string()),
+    })
+}
+
+/// `1.8.0_432` is Java 8, `21.0.5` and `21` are Java 21.
+fn major_version(version: &str) -> Option<u32> {
+    let mut parts = version.split(['.', '_', '-', '+']);
+    match parts.next()? {
+        "1" => parts.next()?.parse().ok(),
+        major => major.parse().ok(),
+    }
+}
@@ -1,12 +1,41 @@
-use crate::models::{Classifiers, Library, VersionDetails};
+use crate::java;
+use crate::log4j::{self, Log4jParser, LogFormat, LogLevel, LogPrinter};
+use crate::models::{Argument, ArgumentValue, VersionDetails};
+use crate::rules::{Features, RuleContext};
+use crate::version;
 use anyhow::Context;
+use std::collections::HashMap;
 use std::fs;
 use std::path::{Path, PathBuf};
-use std::process::Command;
+use std::process::{Command, Stdio};
+use std::time::{Duration, Instant};
+use tokio::io::{AsyncBufReadExt, AsyncRead, BufReader};
 
 #[cfg(target_os = "windows")]
 use std::os::windows::process::CommandExt;
 
+const LAUNCHER_NAME: &str = "mclc";
+const CLASSPATH_SEPARATOR: &str = if cfg!(windows) { ";" } else { ":" };
+
+const LEGACY_JVM_ARGUMENTS: &[&str] = &[
+    "-Djava.library.path=${natives_directory}",
+    "-cp",
+    "${classpath}",
+];
+
+const DEFAULT_GAME_ARGUMENTS: &[&str] = &[
+    "--username", "${auth_player_name}",
+    "--version", "${version_name}",
+    "--gameDir", "${game_directory}",
+    "--assetsDir", "${assets_root}",
+    "--assetIndex", "${assets_index_name}",
+    "--accessToken", "${auth_access_token}",
+    "--clientId", "${clientid}",
+    "--uuid", "${auth_uuid}",
+    "--userType", "${user_type}",
+    "--userProperties", "${user_properties}",
+];
+
 #[derive(Debug)]
 pub struct Launcher {
     pub minecraft_dir: PathBuf,
@@ -15,69 +44,124 @@ pub struct Launcher {
     pub assets_dir: PathBuf,
 }
 
+/// Everything about a single launch that does not come from the version JSON.
+#[derive(Debug, Default)]
+pub struct LaunchOptions {
+    pub username: String,
+    pub access_token: String,
+    pub uuid: String,
+    pub user_type: String,
+    pub jvm_args: Option<String>,
+    pub java_path: Option<String>,
+    /// Maximum heap size, e.g. "4G" (2G when not set)
+    pub memory: Option<String>,
+    /// Where saves, mods and options live (the Minecraft directory when not set)
+    pub game_dir: Option<PathBuf>,
+    pub authlib_injector_jar: Option<PathBuf>,
+    pub prefetched_metadata: Option<String>,
+    pub api_url: Option<String>,
+    pub demo: bool,
+    /// Window size as (width, height)
+    pub resolution: Option<(u32, u32)>,
+    pub quick_play: Option<QuickPlay>,
+    /// Stay attached to the game, stream its output and return its exit code
+    pub wait: bool,
+    /// How the streamed output is printed
+    pub log_format: LogFormat,
+    /// Leave out log events below this level
+    pub log_level: Option<LogLevel>,
+}
+
+/// Where the game should go straight after start-up.
+#[derive(Debug, Clone)]
+pub enum QuickPlay {
+    /// World (save folder) name
+    Singleplayer(String),
+    /// Server address, `host[:port]`
+    Multiplayer(String),
+    /// Realm ID
+    Realms(String),
+}
+
+impl LaunchOptions {
+    pub fn features(&self) -> Features {
+        Features {
+            is_demo_user: self.demo,
+            has_custom_resolution: self.resolution.is_some(),
+            has_quick_plays_support: self.quick_play.is_some(),
+            is_quick_play_singleplayer: matches!(self.quick_play, Some(QuickPlay::Singleplayer(_))),
+            is_quick_play_multiplayer: matches!(self.quick_play, Some(QuickPlay::Multiplayer(_))),
+            is_quick_play_realms: matches!(self.quick_play, Some(QuickPlay::Realms(_))),
+        }
+    }
+}
+
 impl Launcher {
-    pub fn launch_game(
+    pub async fn launch_game(
         &self,
         version_id: &str,
-        username: String,
-        access_token: String,
-        uuid: String,
-        user_type: String,
-        jvm_args: Option<String>,
-        java_path_override: Option<String>,
-        authlib_injector_jar: Option<std::path::PathBuf>,
-        prefetched_metadata: Option<String>,
-        api_url: Option<String>,
-    ) -> anyhow::Result<()> {
+        options: LaunchOptions,
+    ) -> anyhow::Result<Option<i32>> {
         println!(
             "Launching Minecraft version: {} for user: {}",
-            version_id, username
+            version_id, options.username
         );
 
         let version_dir = self.versions_dir.join(version_id);
-        if !version_dir.exists() {
-            return Err(anyhow::anyhow!("Version {} not found", version_id));
-        }
+        let version_details = version::load_version(&self.versions_dir, version_id)?;
 
-        let version_json_path = version_dir.join(format!("{}.json", version_id));
-        let version_json = fs::read_to_string(&version_json_path)?;
-        let version_details: VersionDetails = serde_json::from_str(&version_json)?;
-
-        let java_path = if let Some(override_path) = java_path_override {
+        let java_version = version_details.java_version.as_ref();
+        let java_path = if let Some(override_path) = &options.java_path {
             println!("Using explicitly provided Java path: {}", override_path);
-            PathBuf::from(override_path)
+            let java_path = PathBuf::from(override_path);
+            java::check_java(&java_path, java_version)?;
+            java_path
         } else {
-            self.find_java_from_env()?
+            java::select_java(&self.minecraft_dir.join(java::RUNTIME_DIR), java_version)?.path
         };
 
         println!("Using Java: {:?}", java_path);
 
+        let game_dir = self.game_dir(&options);
+        if options.game_dir.is_some() {
+            println!("Using game directory: {}", game_dir.display());
+        }
+        fs::create_dir_all(game_dir)
+            .with_context(|| format!("Failed to create game directory {:?}", game_dir))?;
+
+        let rules = RuleContext::current(options.features());
+
         // Verify and extract native libraries if needed
         let version_natives_dir = version_dir.join("natives");
         self.verify_and_extract_natives(
-            &version_details, &version_natives_dir)?;
+            &version_details, &version_natives_dir, &rules)?;
 
+        let client_jar = version::client_jar(&self.versions_dir, &version_details)?;
         let classpath = self.build_classpath(
-            &version_dir, &version_details)?;
+            &client_jar, &version_details, &rules)?;
 
-        let mut command_args = self.build_jvm_arguments(
-            jvm_args,
+        let placeholders = self.build_placeholders(
+            version_id,
+            &client_jar,
+            &version_details,
             &version_natives_dir,
-            authlib_injector_jar.as_ref(),
-            prefetched_metadata.as_ref(),
-            api_url.as_deref()
+            classpath,
+            &options,
+        );
+
+        let mut command_args = self.build_jvm_arguments(
+            &version_details,
+            &placeholders,
+            &rules,
+            &options,
         );
-        command_args.push("-cp".to_string());
-        command_args.push(classpath);
         command_args.push(version_details.main_class.clone());
 
         let game_args = self.build_game_args(
-            &username,
-            version_id,
-            &access_token,
-            &uuid,
-            &user_type,
             &version_details,
+            &placeholders,
+            &rules,
+            &options,
         );
         command_args.extend(game_args);
 
@@ -87,10 +171,14 @@ impl Launcher {
             command_args
         );
 
-        // Spawn the game process and let launcher exit
         let mut cmd = Command::new(&java_path);
         cmd.args(&command_args);
+        if options.wait {
+            let printer = |is_stderr| LogPrinter { format: options.log_format, min_level: options.log_level, is_stderr };
+            return run_in_foreground(cmd, printer(false), printer(true)).await.map(Some);
+        }
 
+        // Spawn the game process and let launcher exit
         #[cfg(target_os = "windows")]
         {
             cmd.creation_flags(0x00000008); // DETACHED_PROCESS
@@ -100,13 +188,14 @@ impl Launcher {
 
         println!("Minecraft launched successfully");
         println!("Please wait patiently for the game window to appear");
-        Ok(())
+        Ok(None)
     }
 
     fn verify_and_extract_natives(
         &self,
         version_details: &VersionDetails,
         natives_dir: &Path,
+        rules: &RuleContext,
     ) -> anyhow::Result<()> {
         if !natives_dir.exists() {
             fs::create_dir_all(natives_dir)?;
@@ -115,41 +204,23 @@ impl Launcher {
         let mut needs_extraction = false;
 
         for library in &version_details.libraries {
-            if !self.should_include_library(library) {
+            if !rules.library_allowed(library) {
                 continue;
             }
 
-            if let Some(downloads) = &library.downloads {
-                if let Some(classifiers) = &downloads.classifiers {
-                    if let Some(artifact) = self.get_native_artifact(classifiers) {
-                        let native_path = self.libraries_dir.join(&artifact.path);
-
-                        // Check if native library JAR exists and has been extracted
-                        if native_path.exists() {
-                            // Check if at least one native file exists
-                            let has_natives = self.check_natives_exist(natives_dir);
-                            if !has_natives {
-                                needs_extraction = true;
-                                break;
-                            }
-                        } else {
-                            println!("Warning: Native library not found: {:?}. Please run install first.", native_path);
-                        }
-                    }
+            if let Some(artifact) = rules.native_artifact(library) {
+                let native_path = self.libraries_dir.join(&artifact.path);
 
-                    // Check other natives (natives-windows, natives-linux, etc.)
-                    for (classifier_name, artifact) in &classifiers.other {
-                        if classifier_name.contains("natives-") {
-                            let native_path = self.libraries_dir.join(&artifact.path);
-                            if native_path.exists() {
-                                let has_natives = self.check_natives_exist(natives_dir);
-                                if !has_natives {
-                                    needs_extraction = true;
-                                    break;
-                                }
-                            }
-                        }
+                // Check if native library JAR exists and has been extracted
+                if native_path.exists() {
+                    // Check if at least one native file exists
+                    let has_natives = self.check_natives_exist(natives_dir);
+                    if !has_natives {
+                        needs_extraction = true;
+                        break;
                     }
+                } else {
+                    println!("Warning: Native library not found: {:?}. Please run install first.", native_path);
                 }
             }
         }
@@ -157,29 +228,15 @@ impl Launcher {
         if needs_extraction {
             println!("Extracting native libraries...");
             for library in &version_details.libraries {
-                if !self.should_include_library(library) {
+                if !rules.library_allowed(library) {
                     continue;
                 }
 
-                if let Some(downloads) = &library.downloads {
-                    if let Some(classifiers) = &downloads.classifiers {
-                        if let Some(artifact) = self.get_native_artifact(classifiers) {
-                            let native_path = self.libraries_dir.join(&artifact.path);
-                            if native_path.exists() {
-                                self.extract_lwjgl3_native_library(
-                                    &native_path, natives_dir)?;
-                            }
-                        }
-
-                        for (classifier_name, artifact) in &classifiers.other {
-                            if classifier_name.contains("natives-") {
-                                let native_path = self.libraries_dir.join(&artifact.path);
-                                if native_path.exists() {
-                                    self.extract_lwjgl3_native_library(
-                                        &native_path, natives_dir)?;
-                                }
-                            }
-                        }
+                if let Some(artifact) = rules.native_artifact(library) {
+                    let native_path = self.libraries_dir.join(&artifact.path);
+                    if native_path.exists() {
+                        self.extract_lwjgl3_native_library(
+                            &native_path, natives_dir)?;
                     }
                 }
             }
@@ -215,81 +272,6 @@ impl Launcher {
         false
     }
 
-    fn should_include_library(
-        &self, library: &Library
-    ) -> bool {
-        if let Some(rules) = &library.rules {
-            let mut allowed = false;
-            for rule in rules {
-                let matches = match &rule.os {
-                    Some(os_rule) => {
-                        match std::env::consts::OS {
-                            "windows" => os_rule.name == "windows",
-                            "linux" => os_rule.name == "linux",
-                            "macos" => os_rule.name == "osx",
-                            _ => false,
-                        }
-                    }
-                    None => true,
-                };
-
-                if rule.action == "allow" {
-                    if matches {
-                        allowed = true;
-                    }
-                } else if rule.action == "disallow" {
-                    if matches {
-                        return false;
-                    }
-                }
-            }
-
-            // If there are rules but none allowed, check if default should be disallow
-            // Minecraft's rule system: if action is "allow", it applies when matches
-            // If action is "disallow", it applies when matches
-            // If no rules match, the default behavior depends on the last rule
-            return allowed;
-        }
-        true
-    }
-
-    fn get_native_artifact<'a>(
-        &self, classifiers: &'a Classifiers
-    ) -> Option<&'a crate::models::Artifact> {
-        let os_name = match std::env::consts::OS {
-            "windows" => "windows",
-            "linux" => "linux",
-            "macos" => "macos",
-            _ => return None,
-        };
-        let arch_name = match std::env::consts::ARCH {
-            "aarch64" => "aarch64",
-            "x86_64" => "x64",
-            _ => std::env::consts::ARCH,
-        };
-
-        let specific_key = format!("natives-{}-{}", os_name, arch_name);
-        if let Some(artifact) = classifiers.other.get(&specific_key) {
-            return Some(artifact);
-        }
-
-        match std::env::consts::OS {
-            "windows" => classifiers
-                .natives_windows
-                .as_ref()
-                .or_else(|| classifiers.other.get("natives-windows")),
-            "linux" => classifiers
-                .natives_linux
-                .as_ref()
-                .or_else(|| classifiers.other.get("natives-linux")),
-            "macos" => classifiers
-                .natives_macos
-                .as_ref()
-                .or_else(|| classifiers.other.get("natives-macos")),
-            _ => None,
-        }
-    }
-
     fn extract_lwjgl3_native_library(
         &self, jar_path: &Path, extract_dir: &Path
     ) -> anyhow::Result<()> {
@@ -306,12 +288,11 @@ impl Launcher {
             let file_name = file.name().to_lowercase();
 
             if file_name.ends_with(".dll") || file_name.ends_with(".so") || file_name.ends_with(".dylib") {
-                if let Some(p) = outpath.parent() {
-                    if !p.exists() {
-                        fs::create_dir_all(p).with_context(|| {
-                            format!("Failed to create directory for native file: {:?}", p)
-                        })?;
-                    }
+                if let Some(p) = outpath.parent()
+                    && !p.exists() {
+                    fs::create_dir_all(p).with_context(|| {
+                        format!("Failed to create directory for native file: {:?}", p)
+                    })?;
                 }
                 let mut outfile = std::fs::File::create(&outpath)
                     .with_context(|| format!("Failed to create output file for native: {:?}", outpath))?;
@@ -324,24 +305,23 @@ impl Launcher {
 
     fn build_classpath(
         &self,
-        version_dir: &PathBuf,
+        client_jar: &Path,
         version_details: &VersionDetails,
+        rules: &RuleContext,
     ) -> anyhow::Result<String> {
         let mut classpath = Vec::new();
-        classpath.push(version_dir.join(format!("{}.jar", version_details.id)));
+        classpath.push(client_jar.to_path_buf());
 
         for library in &version_details.libraries {
             // Check if library should be included based on rules
-            if !self.should_include_library(library) {
+            if !rules.library_allowed(library) {
                 continue;
             }
 
-            if let Some(downloads) = &library.downloads {
-                if let Some(artifact) = &downloads.artifact {
-                    let library_path = self.libraries_dir.join(&artifact.path);
-                    if library_path.exists() {
-                        classpath.push(library_path);
-                    }
+            if let Some(artifact_path) = library.artifact_path() {
+                let library_path = self.libraries_dir.join(artifact_path);
+                if library_path.exists() {
+                    classpath.push(library_path);
                 }
             }
         }
@@ -350,20 +330,109 @@ impl Launcher {
             .iter()
             .map(|p| p.to_string_lossy().to_string())
             .collect::<Vec<_>>()
-            .join(if cfg!(windows) { ";" } else { ":" }))
+            .join(CLASSPATH_SEPARATOR))
+    }
+
+    fn build_placeholders(
+        &self,
+        version_id: &str,
+        client_jar: &Path,
+        version_details: &VersionDetails,
+        natives_dir: &Path,
+        classpath: String,
+        options: &LaunchOptions,
+    ) -> HashMap<&'static str, String> {
+        let asset_index_id = version_details
+            .asset_index
+            .as_ref()
+            .map(|ai| ai.id.clone())
+            .unwrap_or_else(|| version_id.to_string());
+        let game_dir = self.game_dir(options);
+        let assets_dir = self.assets_dir.to_string_lossy().to_string();
+        let primary_jar = client_jar;
+        let (width, height) = options.resolution.unwrap_or((854, 480));
+        let (quick_play_world, quick_play_server, quick_play_realm) = match &options.quick_play {
+            Some(QuickPlay::Singleplayer(world)) => (world.clone(), String::new(), String::new()),
+            Some(QuickPlay::Multiplayer(server)) => (String::new(), server.clone(), String::new()),
+            Some(QuickPlay::Realms(realm)) => (String::new(), String::new(), realm.clone()),
+            None => Default::default(),
+        };
+
+        HashMap::from([
+            ("auth_player_name", options.username.clone()),
+            ("version_name", version_id.to_string()),
+            ("version_type", version_details.version_type.clone()),
+            ("game_directory", game_dir.to_string_lossy().to_string()),
+            ("assets_root", assets_dir.clone()),
+            ("game_assets", assets_dir),
+            ("assets_index_name", asset_index_id),
+            ("auth_uuid", options.uuid.clone()),
+            ("auth_access_token", options.access_token.clone()),
+            ("auth_session", format!("token:{}:{}", options.access_token, options.uuid)),
+            ("auth_xuid", "0".to_string()),
+            ("clientid", "0".to_string()),
+            ("user_type", options.user_type.clone()),
+            ("user_properties", "{}".to_string()),
+            ("natives_directory", natives_dir.to_string_lossy().to_string()),
+            ("launcher_name", LAUNCHER_NAME.to_string()),
+            ("launcher_version", env!("CARGO_PKG_VERSION").to_string()),
+            ("classpath", classpath),
+            ("classpath_separator", CLASSPATH_SEPARATOR.to_string()),
+            ("library_directory", self.libraries_dir.to_string_lossy().to_string()),
+            ("primary_jar", primary_jar.to_string_lossy().to_string()),
+            ("resolution_width", width.to_string()),
+            ("resolution_height", height.to_string()),
+            ("quickPlayPath", game_dir.join("quickPlay").join("log.json").to_string_lossy().to_string()),
+            ("quickPlaySingleplayer", quick_play_world),
+            ("quickPlayMultiplayer", quick_play_server),
+            ("quickPlayRealms", quick_play_realm),
+        ])
+    }
+
+    /// Evaluates the rules of every entry and substitutes `${...}` placeholders.
+    fn resolve_arguments(
+        &self,
+        arguments: &[Argument],
+        placeholders: &HashMap<&'static str, String>,
+        rules: &RuleContext,
+    ) -> Vec<String> {
+        let mut resolved = Vec::new();
+        for argument in arguments {
+            match argument {
+                Argument::Plain(value) => {
+                    resolved.push(substitute_placeholders(value, placeholders));
+                }
+                Argument::Conditional { rules: argument_rules, value } => {
+                    if !rules.allows(argument_rules) {
+                        continue;
+                    }
+                    match value {
+                        ArgumentValue::Single(value) => {
+                            resolved.push(substitute_placeholders(value, placeholders));
+                        }
+                        ArgumentValue::Multiple(values) => {
+                            resolved.extend(values.iter().map(|v| substitute_placeholders(v, placeholders)));
+                        }
+                    }
+                }
+            }
+        }
+        resolved
     }
 
     fn build_jvm_arguments(
         &self,
-        custom_args: Option<String>,
-        natives_dir: &PathBuf,
-        authlib_injector_jar: Option<&std::path::PathBuf>,
-        prefetched_metadata: Option<&String>,
-        api_url: Option<&str>,
+        version_details: &VersionDetails,
+        placeholders: &HashMap<&'static str, String>,
+        rules: &RuleContext,
+        options: &LaunchOptions,
     ) -> Vec<String> {
-        let mut args = vec![
-            "-Xmx2G".to_string(),
-            "-Xms1G".to_string(),
+        let memory = match &options.memory {
+            Some(memory) => vec![format!("-Xmx{}", memory)],
+            None => vec!["-Xmx2G".to_string(), "-Xms1G".to_string()],
+        };
+        let mut args = memory;
+        args.extend([
             "-XX:+UnlockExperimentalVMOptions".to_string(),
             "-XX:+UnlockDiagnosticVMOptions".to_string(),
             "-XX:+UseG1GC".to_string(),
@@ -379,90 +448,217 @@ impl Launcher {
             "-XX:TieredCompileTaskTimeout=10000".to_string(),
             "-XX:ReservedCodeCacheSize=400M".to_string(),
             "-XX:NmethodSweepActivity=1".to_string(),
-            format!("-Djava.library.path={}", natives_dir.to_string_lossy()),
-        ];
+        ]);
 
         // Add authlib-injector arguments if provided
-        if let (Some(jar_path), Some(prefetched)) = (authlib_injector_jar, prefetched_metadata) {
+        if let Some(jar_path) = &options.authlib_injector_jar {
             // -javaagent:{jar_path}={api_url}
             // -Dauthlibinjector.yggdrasil.prefetched={base64_metadata}
-            let api = api_url.unwrap_or("");
+            let api = options.api_url.as_deref().unwrap_or("");
             args.insert(6, format!("-javaagent:{}={}", jar_path.display(), api));
-            args.insert(7, format!("-Dauthlibinjector.yggdrasil.prefetched={}", prefetched));
+            if let Some(prefetched) = &options.prefetched_metadata {
+                args.insert(7, format!("-Dauthlibinjector.yggdrasil.prefetched={}", prefetched));
+            }
             println!("Using authlib-injector: {} with API: {}", jar_path.display(), api);
         }
 
-        if let Some(custom) = custom_args {
+        // Versions before 1.13 have no `arguments.jvm`, so use what the official launcher passes to them.
+        // Loader profiles on top of such versions add some, but never the classpath.
+        let jvm_arguments = version_details
+            .arguments
+            .as_ref()
+            .map(|arguments| arguments.jvm.as_slice())
+            .unwrap_or_default();
+        if !jvm_arguments.iter().any(|arg| arg.mentions("${classpath}")) {
+            args.extend(
+                LEGACY_JVM_ARGUMENTS
+                    .iter()
+                    .map(|arg| substitute_placeholders(arg, placeholders)),
+            );
+        }
+        args.extend(self.resolve_arguments(jvm_arguments, placeholders, rules));
+
+        // The XML events are only readable when mclc stays attached to parse them
+        if options.wait
+            && let Some(config) = version_details.logging.as_ref().and_then(|logging| logging.client.as_ref()) {
+            let config_path = self.assets_dir.join(log4j::LOG_CONFIGS_DIR).join(&config.file.id);
+            if config_path.is_file() {
+                args.push(config.argument.replace("${path}", &config_path.to_string_lossy()));
+            } else {
+                eprintln!("Log config {} is missing, install the version again to download it", config.file.id);
+            }
+        }
+
+        if let Some(custom) = &options.jvm_args {
             args.extend(custom.split_whitespace().map(String::from));
         }
 
         args
     }
 
+    fn game_dir<'a>(&'a self, options: &'a LaunchOptions) -> &'a Path {
+        options.game_dir.as_deref().unwrap_or(&self.minecraft_dir)
+    }
+
     fn build_game_args(
         &self,
-        username: &str,
-        version_id: &str,
-        access_token: &str,
-        uuid: &str,
-        user_type: &str,
         version_details: &VersionDetails,
+        placeholders: &HashMap<&'static str, String>,
+        rules: &RuleContext,
+        options: &LaunchOptions,
     ) -> Vec<String> {
-        let client_id = "0";
-        let asset_index_id = version_details
-            .asset_index
-            .as_ref()
-            .map(|ai| ai.id.clone())
-            .unwrap_or_else(|| version_id.to_string());
+        if let Some(arguments) = &version_details.arguments
+            && !arguments.game.is_empty() {
+            return self.resolve_arguments(&arguments.game, placeholders, rules);
+        }
+
+        // Pre-1.13 versions describe their arguments as a single space-separated string
+        let mut args: Vec<String> = match &version_details.minecraft_arguments {
+            Some(minecraft_arguments) => minecraft_arguments
+                .split_whitespace()
+                .map(|arg| substitute_placeholders(arg, placeholders))
+                .collect(),
+            None => DEFAULT_GAME_ARGUMENTS
+                .iter()
+                .map(|arg| substitute_placeholders(arg, placeholders))
+                .collect(),
+        };
 
-        vec![
-            "--username".to_string(),
-            username.to_string(),
-            "--version".to_string(),
-            version_id.to_string(),
-            "--gameDir".to_string(),
-            self.minecraft_dir.to_string_lossy().to_string(),
-            "--assetsDir".to_string(),
-            self.assets_dir.to_string_lossy().to_string(),
-            "--assetIndex".to_string(),
-            asset_index_id,
-            "--accessToken".to_string(),
-            access_token.to_string(),
-            "--clientId".to_string(),
-            client_id.to_string(),
-            "--uuid".to_string(),
-            uuid.to_string(),
-            "--userType".to_string(),
-            user_type.to_string(),
-            "--userProperties".to_string(),
-            "{}".to_string(),
-        ]
+        // Without `arguments.game` there are no feature-guarded entries, so add them the way the official launcher does
+        if options.demo {
+            args.push("--demo".to_string());
+        }
+        if let Some((width, height)) = options.resolution {
+            args.extend(["--width".to_string(), width.to_string(), "--height".to_string(), height.to_string()]);
+        }
+        args
     }
 
-    fn find_java_from_env(
-        &self
-    ) -> anyhow::Result<PathBuf> {
-        if let Ok(java_home) = std::env::var("JAVA_HOME") {
-            let java_home_path = PathBuf::from(java_home);
-            let java_bin = java_home_path.join("bin").join(if cfg!(target_os = "windows") {
-                "java.exe"
-            } else {
-                "java"
-            });
+}
 
-            if java_bin.exists() {
-                println!("Found Java in JAVA_HOME: {:?}", java_home_path);
-                return Ok(java_bin);
-            } else {
-                eprintln!(
-                    "JAVA_HOME is set to '{:?}' but Java executable not found at '{:?}'",
-                    java_home_path, java_bin
-                );
+/// Runs the game attached to mclc: its output is streamed line by line with a prefix, Ctrl-C, SIGTERM
+/// and SIGHUP are passed on to it, and its exit code is returned once it exits.
+async fn run_in_foreground(mut cmd: Command, stdout_printer: LogPrinter, stderr_printer: LogPrinter) -> anyhow::Result<i32> {
+    cmd.stdout(Stdio::piped()).stderr(Stdio::piped());
+    // In its own process group the game only gets the signals mclc passes on, not a second Ctrl-C from the terminal
+    #[cfg(unix)]
+    {
+        use std::os::unix::process::CommandExt;
+        cmd.process_group(0);
+    }
+    let mut child = tokio::process::Command::from(cmd).spawn().context("Failed to start Minecraft")?;
+    let started = Instant::now();
+    println!("Minecraft started, waiting for it to exit");
+
+    let stdout = child.stdout.take().map(|stdout| stream_output(stdout, stdout_printer));
+    let stderr = child.stderr.take().map(|stderr| stream_output(stderr, stderr_printer));
+
+    #[cfg(unix)]
+    let (mut interrupt, mut terminate, mut hangup) = {
+        use tokio::signal::unix::{SignalKind, signal};
+        (signal(SignalKind::interrupt())?, signal(SignalKind::terminate())?, signal(SignalKind::hangup())?)
+    };
+    let status = loop {
+        #[cfg(unix)]
+        tokio::select! {
+            status = child.wait() => break status?,
+            _ = interrupt.recv() => forward_signal(&child, libc::SIGINT, "SIGINT"),
+            _ = terminate.recv() => forward_signal(&child, libc::SIGTERM, "SIGTERM"),
+            // Closing the terminal would otherwise leave the game running without mclc
+            _ = hangup.recv() => forward_signal(&child, libc::SIGHUP, "SIGHUP"),
+        }
+        // The console delivers Ctrl-C to the game as well, so just keep waiting for it to exit
+        #[cfg(not(unix))]
+        tokio::select! {
+            status = child.wait() => break status?,
+            _ = tokio::signal::ctrl_c() => println!("Waiting for Minecraft to shut down"),
+        }
+    };
+    // The pipes close with the game, so the rest of its output is flushed here
+    for stream in [stdout, stderr].into_iter().flatten() {
+        let _ = stream.await;
+    }
+
+    let code = match status.code() {
+        Some(code) => code,
+        None => {
+            #[cfg(unix)]
+            {
+                use std::os::unix::process::ExitStatusExt;
+                128 + status.signal().unwrap_or(0)
             }
-        } else {
-            eprintln!("JAVA_HOME environment variable is not set.");
+            #[cfg(not(unix))]
+            1
+        }
+    };
+    println!("Minecraft exited with code {} after {}", code, format_duration(started.elapsed()));
+    Ok(code)
+}
+
+/// Prints every line the game writes, turning Log4j XML events back into log lines.
+fn stream_output<R: AsyncRead + Unpin + Send + 'static>(reader: R, printer: LogPrinter) -> tokio::task::JoinHandle<()> {
+    tokio::spawn(async move {
+        let mut parser = Log4jParser::default();
+        // Not every line the game prints is valid UTF-8
+        let mut lines = BufReader::new(reader).split(b'\n');
+        while let Ok(Some(line)) = lines.next_segment().await {
+            let line = String::from_utf8_lossy(&line);
+            if let Some(record) = parser.push_line(line.trim_end_matches('\r')) {
+                printer.print(&record);
+            }
+        }
+        if let Some(record) = parser.finish() {
+            printer.print(&record);
         }
+    })
+}
+
+#[cfg(unix)]
+fn forward_signal(child: &tokio::process::Child, signal: libc::c_int, name: &str) {
+    if let Some(pid) = child.id() {
+        println!("Passing {} on to Minecraft", name);
+        // SAFETY: kill has no memory safety requirements, the pid is our own running child
+        unsafe {
+            libc::kill(pid as libc::pid_t, signal);
+        }
+    }
+}
+
+/// `1h 02m 03s`, `2m 03s` or `3s`.
+fn format_duration(duration: Duration) -> String {
+    let seconds = duration.as_secs();
+    let (hours, minutes, seconds) = (seconds / 3600, seconds / 60 % 60, seconds % 60);
+    if hours > 0 {
+        format!("{}h {:02}m {:02}s", hours, minutes, seconds)
+    } else if minutes > 0 {
+        format!("{}m {:02}s", minutes, seconds)
+    } else {
+        format!("{}s", seconds)
+    }
+}
 
-        Err(anyhow::anyhow!("Java not found. Please set JAVA_HOME or use --runtime"))
+/// Replaces every `${name}` in `template` with its value; unknown placeholders are kept verbatim.
+fn substitute_placeholders(template: &str, placeholders: &HashMap<&'static str, String>) -> String {
+    let mut result = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(start) = rest.find("${") {
+        result.push_str(&rest[..start]);
+        let after = &rest[start + 2..];
+        match after.find('}') {
+            Some(end) => {
+                let key = &after[..end];
+                match placeholders.get(key) {
+                    Some(value) => result.push_str(value),
+                    None => result.push_str(&rest[start..start + 2 + end + 1]),
+                }
+                rest = &after[end + 1..];
+            }
+            None => {
+                result.push_str(&rest[start..]);
+                rest = "";
+            }
+        }
     }
+    result.push_str(rest);
+    result
 }
@@ -1,41 +1,75 @@
 use crate::auth::Authenticator;
-use crate::install::Installer;
-use crate::launch::Launcher;
-use crate::models::AuthCache;
+use crate::curseforge::{CURSEFORGE_API_URL, CurseForgeApi};
+use crate::install::{DEFAULT_DOWNLOAD_RETRIES, Installer, ListOptions};
+use crate::java;
+use crate::launch::{LaunchOptions, Launcher};
+use crate::loader::{FABRIC_META_URL, LoaderSpec, QUILT_META_URL};
+use crate::log4j;
+use crate::mirror::DownloadSource;
+use crate::models::{AuthCache, Instance, LauncherConfig, ModpackRecord};
+use crate::modpack::{self, ExportOptions};
 use crate::yggdrasil::{AuthlibInjector, YggdrasilAccount, YggdrasilAuthenticator};
+use anyhow::Context;
 use std::fs;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+
+/// Environment variable naming the Minecraft directory.
+pub const MINECRAFT_DIR_ENV: &str = "MCLC_HOME";
 
 #[derive(Debug)]
 pub struct LauncherManager {
     pub launcher: Launcher,
     pub installer: Installer,
     pub authenticator: Authenticator,
+    pub curseforge: CurseForgeApi,
     pub config_dir: PathBuf,
 }
 
 impl LauncherManager {
-    pub fn new() -> anyhow::Result<Self> {
-        let current_dir = std::env::current_dir()?;
-        let minecraft_dir = current_dir.join(".minecraft");
-        fs::create_dir_all(&minecraft_dir)?;
+    /// Sets up the launcher on `minecraft_dir`, or on the directory named by `MCLC_HOME`, `minecraft_dir`
+    /// in config.json or the platform data directory, in that order.
+    pub fn new(minecraft_dir: Option<PathBuf>) -> anyhow::Result<Self> {
+        let config_dir = dirs::config_dir()
+            .ok_or_else(|| anyhow::anyhow!("Could not determine config directory"))?
+            .join("mclc");
+        fs::create_dir_all(&config_dir)?;
+        let config = Self::load_config(&config_dir)?;
+        let source = match &config.source {
+            Some(source) => source
+                .parse::<DownloadSource>()
+                .map_err(|e| anyhow::anyhow!("Invalid source in config.json: {}", e))?,
+            None => DownloadSource::Official,
+        };
+
+        let minecraft_dir = match minecraft_dir
+            .or_else(|| std::env::var_os(MINECRAFT_DIR_ENV).filter(|dir| !dir.is_empty()).map(PathBuf::from))
+            .or_else(|| config.minecraft_dir.as_ref().map(PathBuf::from))
+        {
+            Some(dir) => dir,
+            None => {
+                let default_dir = dirs::data_dir()
+                    .ok_or_else(|| anyhow::anyhow!("Could not determine data directory, use --minecraft-dir"))?
+                    .join("mclc");
+                // Earlier versions kept everything in .minecraft under the working directory
+                let legacy_dir = std::env::current_dir()?.join(".minecraft");
+                if legacy_dir.is_dir() && !default_dir.exists() {
+                    eprintln!("Found {}, pass --minecraft-dir {} to use it", legacy_dir.display(), legacy_dir.display());
+                }
+                default_dir
+            }
+        };
+        // An existing directory, e.g. the official launcher's .minecraft, is used as it is;
+        // subdirectories are only created once something is written to them
+        fs::create_dir_all(&minecraft_dir)
+            .with_context(|| format!("Failed to create Minecraft directory {:?}", minecraft_dir))?;
+        let minecraft_dir = std::path::absolute(&minecraft_dir)?;
 
         let versions_dir = minecraft_dir.join("versions");
         let libraries_dir = minecraft_dir.join("libraries");
         let assets_dir = minecraft_dir.join("assets");
         let assets_objects_dir = assets_dir.join("objects");
         let assets_indexes_dir = assets_dir.join("indexes");
-
-        let config_dir = dirs::config_dir()
-            .ok_or_else(|| anyhow::anyhow!("Could not determine config directory"))?
-            .join("mclc");
-        fs::create_dir_all(&config_dir)?;
-
-        fs::create_dir_all(&versions_dir)?;
-        fs::create_dir_all(&libraries_dir)?;
-        fs::create_dir_all(&assets_dir)?;
-        fs::create_dir_all(&assets_objects_dir)?;
-        fs::create_dir_all(&assets_indexes_dir)?;
+        let log_configs_dir = assets_dir.join(log4j::LOG_CONFIGS_DIR);
 
         Ok(Self {
             launcher: Launcher {
@@ -49,12 +83,36 @@ impl LauncherManager {
                 libraries_dir,
                 assets_objects_dir,
                 assets_indexes_dir,
+                log_configs_dir,
+                max_retries: DEFAULT_DOWNLOAD_RETRIES,
+                sources: source.fallback_order(),
+                cache_dir: config_dir.join("cache"),
+                runtime_dir: minecraft_dir.join(java::RUNTIME_DIR),
+                offline: false,
+                fabric_meta_url: config.fabric_meta_url.clone().unwrap_or_else(|| FABRIC_META_URL.to_string()),
+                quilt_meta_url: config.quilt_meta_url.clone().unwrap_or_else(|| QUILT_META_URL.to_string()),
+                java_path: None,
             },
             authenticator: Authenticator::default(),
+            curseforge: CurseForgeApi {
+                api_url: config.curseforge_api_url.clone().unwrap_or_else(|| CURSEFORGE_API_URL.to_string()),
+                api_key: config.curseforge_api_key.clone(),
+            },
             config_dir,
         })
     }
 
+    fn load_config(config_dir: &Path) -> anyhow::Result<LauncherConfig> {
+        let config_path = config_dir.join("config.json");
+        if !config_path.exists() {
+            return Ok(LauncherConfig::default());
+        }
+        let json = fs::read_to_string(&config_path)?;
+        let config: LauncherConfig = serde_json::from_str(&json)
+            .with_context(|| format!("Failed to parse {:?}", config_path))?;
+        Ok(config)
+    }
+
     #[allow(dead_code)]
     pub fn set_client_id(&mut self, client_id: String) {
         self.authenticator = Authenticator::new(client_id);
@@ -174,48 +232,150 @@ impl LauncherManager {
         Ok(())
     }
 
-    pub async fn list_versions(&self) -> anyhow::Result<()> {
-        self.installer.list_versions().await
+    pub async fn list_versions(&self, options: &ListOptions) -> anyhow::Result<()> {
+        self.installer.list_versions(options).await
+    }
+
+    pub async fn install_loader(&self, game_version: &str, spec: &LoaderSpec) -> anyhow::Result<String> {
+        self.installer.install_loader(game_version, spec).await
+    }
+
+    pub async fn install_forge(&self, installer: &str, game_version: Option<&str>) -> anyhow::Result<String> {
+        self.installer.install_forge(installer, game_version).await
+    }
+
+    /// Game directory of a named instance, under `instances/` next to `versions/`.
+    pub fn instance_dir(&self, name: &str) -> anyhow::Result<PathBuf> {
+        if name.is_empty() || name == "." || name == ".." || name.contains(['/', '\\']) {
+            anyhow::bail!("Invalid instance name '{}'", name);
+        }
+        Ok(self.launcher.minecraft_dir.join("instances").join(name))
+    }
+
+    /// Game directory of an instance: its own setting, or the default under `instances/`.
+    pub fn instance_game_dir(&self, instance: &Instance) -> anyhow::Result<PathBuf> {
+        match &instance.game_dir {
+            Some(game_dir) => Ok(game_dir.clone()),
+            None => self.instance_dir(&instance.name),
+        }
+    }
+
+    /// Installs a modpack into an instance, creating the instance or pointing it at the pack's version.
+    pub async fn install_modpack(&self, pack_path: &Path, name: &str) -> anyhow::Result<ModpackRecord> {
+        let existing = self.find_instance(name)?;
+        let game_dir = match &existing {
+            Some(instance) => self.instance_game_dir(instance)?,
+            None => self.instance_dir(name)?,
+        };
+        fs::create_dir_all(&game_dir)?;
+        let record = modpack::install_modpack(&self.installer, &self.curseforge, pack_path, &game_dir).await?;
+
+        let instance = match existing {
+            Some(instance) => Instance { version: record.version_id.clone(), ..instance },
+            None => Instance {
+                name: name.to_string(),
+                version: record.version_id.clone(),
+                game_dir: None,
+                jvm_args: None,
+                java_path: None,
+                memory: None,
+                width: None,
+                height: None,
+            },
+        };
+        self.save_instance(&instance)?;
+        Ok(record)
+    }
+
+    pub fn export_modpack(&self, name: &str, output: &Path, options: &ExportOptions) -> anyhow::Result<(usize, usize)> {
+        let instance = self.find_instance(name)?;
+        let game_dir = match &instance {
+            Some(instance) => self.instance_game_dir(instance)?,
+            None => self.instance_dir(name)?,
+        };
+        if !game_dir.is_dir() {
+            anyhow::bail!("Instance {} does not exist", name);
+        }
+        // Without a modpack record the dependencies come from the version the instance runs
+        let mut options = options.clone();
+        if options.version_id.is_none() && modpack::load_record(&game_dir)?.is_none() {
+            options.version_id = instance.map(|instance| instance.version);
+        }
+        modpack::export_mrpack(&self.installer.versions_dir, name, &game_dir, output, &options)
+    }
+
+    pub fn get_instances_path(&self) -> PathBuf {
+        self.config_dir.join("instances.json")
+    }
+
+    pub fn load_instances(&self) -> anyhow::Result<Vec<Instance>> {
+        let instances_path = self.get_instances_path();
+        if !instances_path.exists() {
+            return Ok(Vec::new());
+        }
+        let json = fs::read_to_string(&instances_path)?;
+        let instances: Vec<Instance> = serde_json::from_str(&json)
+            .with_context(|| format!("Failed to parse {:?}", instances_path))?;
+        Ok(instances)
+    }
+
+    pub fn save_instances(&self, instances: &[Instance]) -> anyhow::Result<()> {
+        let json = serde_json::to_string_pretty(instances)?;
+        fs::write(self.get_instances_path(), json)?;
+        Ok(())
+    }
+
+    pub fn find_instance(&self, name: &str) -> anyhow::Result<Option<Instance>> {
+        Ok(self.load_instances()?.into_iter().find(|instance| instance.name == name))
+    }
+
+    /// Adds the instance, replacing one with the same name.
+    pub fn save_instance(&self, instance: &Instance) -> anyhow::Result<()> {
+        self.instance_dir(&instance.name)?;
+        let mut instances = self.load_instances()?;
+        match instances.iter_mut().find(|existing| existing.name == instance.name) {
+            Some(existing) => *existing = instance.clone(),
+            None => instances.push(instance.clone()),
+        }
+        self.save_instances(&instances)
+    }
+
+    /// Forgets an instance; its game directory is left alone. Returns whether it existed.
+    pub fn remove_instance(&self, name: &str) -> anyhow::Result<bool> {
+        let mut instances = self.load_instances()?;
+        let count = instances.len();
+        instances.retain(|instance| instance.name != name);
+        if instances.len() == count {
+            return Ok(false);
+        }
+        self.save_instances(&instances)?;
+        Ok(true)
+    }
+
+    pub async fn resolve_version_id(&self, version_id: &str) -> anyhow::Result<String> {
+        self.installer.resolve_version_id(version_id).await
     }
 
     pub async fn install_version(&self, version_id: &str) -> anyhow::Result<()> {
         self.installer.install_version(version_id).await
     }
 
+    pub async fn verify_version(&self, version_id: &str, repair: bool) -> anyhow::Result<bool> {
+        self.installer.verify_version(version_id, repair).await
+    }
+
     pub async fn login(&self) -> anyhow::Result<AuthCache> {
         self.authenticator.perform_full_authentication().await
     }
 
-    pub fn launch(
-        &self,
-        version_id: &str,
-        username: String,
-        access_token: String,
-        uuid: String,
-        user_type: String,
-        jvm_args: Option<String>,
-        java_path: Option<String>,
-        authlib_injector_jar: Option<std::path::PathBuf>,
-        prefetched_metadata: Option<String>,
-        api_url: Option<String>,
-    ) -> anyhow::Result<()> {
-        self.launcher.launch_game(
-            version_id,
-            username,
-            access_token,
-            uuid,
-            user_type,
-            jvm_args,
-            java_path,
-            authlib_injector_jar,
-            prefetched_metadata,
-            api_url,
-        )
+    /// Launches a version; with `options.wait` the game's exit code is returned once it exits.
+    pub async fn launch(&self, version_id: &str, options: LaunchOptions) -> anyhow::Result<Option<i32>> {
+        self.launcher.launch_game(version_id, options).await
     }
 }
 
 impl Default for LauncherManager {
     fn default() -> Self {
-        Self::new().expect("Failed to initialize LauncherManager")
+        Self::new(None).expect("Failed to initialize LauncherManager")
     }
 }
@@ -0,0 +1,121 @@
+use std::fmt;
+use std::str::FromStr;
+
+pub const FABRIC_META_URL: &str = "https://meta.fabricmc.net";
+pub const QUILT_META_URL: &str = "https://meta.quiltmc.org";
+
+/// A mod loader that can be installed on top of a Minecraft version.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Loader {
+    Fabric,
+    Quilt,
+    Forge,
+    NeoForge,
+}
+
+impl Loader {
+    pub const ALL: [Loader; 4] = [Loader::Fabric, Loader::Quilt, Loader::Forge, Loader::NeoForge];
+
+    /// Key of the loader in the `dependencies` of a Modrinth pack.
+    pub fn mrpack_dependency(&self) -> &'static str {
+        match self {
+            Loader::Fabric => "fabric-loader",
+            Loader::Quilt => "quilt-loader",
+            Loader::Forge => "forge",
+            Loader::NeoForge => "neoforge",
+        }
+    }
+
+    /// Versioned root of the loader's meta API under `base_url`.
+    /// Forge and NeoForge have none, they ship installers instead.
+    pub fn meta_api(&self, base_url: &str) -> Option<String> {
+        let version = match self {
+            Loader::Fabric => "v2",
+            Loader::Quilt => "v3",
+            Loader::Forge | Loader::NeoForge => return None,
+        };
+        Some(format!("{}/{}", base_url.trim_end_matches('/'), version))
+    }
+
+    /// Id the loader's profile is usually published under.
+    pub fn profile_id(&self, loader_version: &str, game_version: &str) -> String {
+        match self {
+            Loader::Fabric => format!("fabric-loader-{}-{}", loader_version, game_version),
+            Loader::Quilt => format!("quilt-loader-{}-{}", loader_version, game_version),
+            Loader::Forge => format!("{}-forge-{}", game_version, loader_version),
+            Loader::NeoForge => format!("neoforge-{}", loader_version),
+        }
+    }
+
+    /// The loader and its version when the library `name` (a Maven coordinate) is the loader itself,
+    /// as found among the libraries of an installed loader profile.
+    pub fn from_library(name: &str) -> Option<(Loader, String)> {
+        let coordinate = name.split('@').next()?;
+        let [group, artifact, version, ..] = coordinate.split(':').collect::<Vec<_>>()[..] else {
+            return None;
+        };
+        let loader = match (group, artifact) {
+            ("net.fabricmc", "fabric-loader") => Loader::Fabric,
+            ("org.quiltmc", "quilt-loader") => Loader::Quilt,
+            // Forge versions start with the Minecraft version (1.20.1-47.2.0)
+            ("net.minecraftforge", "forge") => {
+                let version = version.split_once('-').map_or(version, |(_, version)| version);
+                return Some((Loader::Forge, version.to_string()));
+            }
+            ("net.neoforged", "neoforge") => Loader::NeoForge,
+            _ => return None,
+        };
+        Some((loader, version.to_string()))
+    }
+
+    /// Maven coordinate of the Forge or NeoForge installer for a loader version.
+    pub fn installer_coordinate(&self, loader_version: &str, game_version: &str) -> Option<String> {
+        match self {
+            Loader::Forge => Some(format!("net.minecraftforge:forge:{}-{}", game_version, loader_version)),
+            // NeoForge versions already encode the Minecraft version (21.1.77 is for 1.21.1)
+            Loader::NeoForge => Some(format!("net.neoforged:neoforge:{}", loader_version)),
+            _ => None,
+        }
+    }
+}
+
+/// `--loader` value: a loader and optionally the loader version, e.g. `fabric@0.16.9`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LoaderSpec {
+    pub loader: Loader,
+    /// Latest stable when not given (required for Forge and NeoForge)
+    pub version: Option<String>,
+}
+
+impl FromStr for LoaderSpec {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (name, version) = match s.split_once('@') {
+            Some((name, version)) if !version.is_empty() => (name, Some(version.to_string())),
+            Some(_) => return Err(format!("missing loader version in '{}'", s)),
+            None => (s, None),
+        };
+        let loader = match name.to_lowercase().as_str() {
+            "fabric" => Loader::Fabric,
+            "quilt" => Loader::Quilt,
+            "forge" => Loader::Forge,
+            "neoforge" => Loader::NeoForge,
+            other => {
+                return Err(format!("unknown loader '{}', expected 'fabric', 'quilt', 'forge' or 'neoforge'", other));
+            }
+        };
+        Ok(LoaderSpec { loader, version })
+    }
+}
+
+impl fmt::Display for Loader {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Loader::Fabric => write!(f, "Fabric"),
+            Loader::Quilt => write!(f, "Quilt"),
+            Loader::Forge => write!(f, "Forge"),
+            Loader::NeoForge => write!(f, "NeoForge"),
+        }
+    }
+}
@@ -0,0 +1,336 @@
+use regex::Regex;
+use serde::Serialize;
+use std::io::IsTerminal;
+use std::sync::LazyLock;
+
+/// Directory inside `assets` holding the Log4j configs named by `logging.client.file`.
+pub const LOG_CONFIGS_DIR: &str = "log_configs";
+
+static ATTRIBUTE: LazyLock<Regex> = LazyLock::new(|| Regex::new(r#"([\w:]+)="([^"]*)""#).expect("valid regex"));
+static MESSAGE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"(?s)<log4j:Message>(.*?)</log4j:Message>").expect("valid regex"));
+static THROWABLE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"(?s)<log4j:Throwable>(.*?)</log4j:Throwable>").expect("valid regex"));
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Serialize, clap::ValueEnum)]
+#[serde(rename_all = "UPPERCASE")]
+pub enum LogLevel {
+    Trace,
+    Debug,
+    Info,
+    Warn,
+    Error,
+    Fatal,
+}
+
+impl LogLevel {
+    fn parse(level: &str) -> Option<Self> {
+        match level {
+            "TRACE" => Some(LogLevel::Trace),
+            "DEBUG" => Some(LogLevel::Debug),
+            "INFO" => Some(LogLevel::Info),
+            "WARN" => Some(LogLevel::Warn),
+            "ERROR" => Some(LogLevel::Error),
+            "FATAL" => Some(LogLevel::Fatal),
+            _ => None,
+        }
+    }
+
+    fn as_str(self) -> &'static str {
+        match self {
+            LogLevel::Trace => "TRACE",
+            LogLevel::Debug => "DEBUG",
+            LogLevel::Info => "INFO",
+            LogLevel::Warn => "WARN",
+            LogLevel::Error => "ERROR",
+            LogLevel::Fatal => "FATAL",
+        }
+    }
+
+    /// ANSI color the level is shown in.
+    fn color(self) -> &'static str {
+        match self {
+            LogLevel::Trace | LogLevel::Debug => "\x1b[2m",
+            LogLevel::Info => "",
+            LogLevel::Warn => "\x1b[33m",
+            LogLevel::Error | LogLevel::Fatal => "\x1b[31m",
+        }
+    }
+}
+
+/// How `launch --wait` prints the game's output.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum LogFormat {
+    /// One readable line per event, colored by level on a terminal
+    #[default]
+    Text,
+    /// One JSON object per line
+    Json,
+}
+
+/// One line of game output: a Log4j event, or a plain line printed outside of Log4j.
+#[derive(Debug, Clone, Serialize)]
+pub struct LogRecord {
+    /// Milliseconds since the Unix epoch
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub timestamp: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub level: Option<LogLevel>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub thread: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub logger: Option<String>,
+    pub message: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub throwable: Option<String>,
+}
+
+impl LogRecord {
+    fn plain(line: &str) -> Self {
+        LogRecord {
+            timestamp: None,
+            level: None,
+            thread: None,
+            logger: None,
+            message: line.to_string(),
+            throwable: None,
+        }
+    }
+}
+
+/// Turns the `<log4j:Event>` stream the game prints with the `logging.client` config back into records.
+/// Events span several lines, so lines are fed in one at a time.
+#[derive(Debug, Default)]
+pub struct Log4jParser {
+    /// The event being read, from its opening tag on
+    pending: Option<String>,
+}
+
+impl Log4jParser {
+    /// Feeds one line of output, returning a record once one is complete.
+    pub fn push_line(&mut self, line: &str) -> Option<LogRecord> {
+        match &mut self.pending {
+            Some(event) => {
+                event.push('\n');
+                event.push_str(line);
+            }
+            None if line.trim_start().starts_with("<log4j:Event") => self.pending = Some(line.to_string()),
+            None => return Some(LogRecord::plain(line)),
+        }
+        if line.contains("</log4j:Event>") {
+            return self.pending.take().map(|event| parse_event(&event));
+        }
+        None
+    }
+
+    /// Whatever is left when the output ends, e.g. an event cut off by a crash.
+    pub fn finish(&mut self) -> Option<LogRecord> {
+        self.pending.take().map(|event| LogRecord::plain(&event))
+    }
+}
+
+fn parse_event(event: &str) -> LogRecord {
+    let start_tag = &event[..event.find('>').unwrap_or(event.len())];
+    let attribute = |name: &str| {
+        ATTRIBUTE
+            .captures_iter(start_tag)
+            .find(|captures| &captures[1] == name)
+            .map(|captures| unescape(&captures[2]))
+    };
+    let text = |regex: &Regex| regex.captures(event).map(|captures| element_text(&captures[1]));
+    LogRecord {
+        timestamp: attribute("timestamp").and_then(|timestamp| timestamp.parse().ok()),
+        level: attribute("level").and_then(|level| LogLevel::parse(&level)),
+        thread: attribute("thread"),
+        logger: attribute("logger"),
+        message: text(&MESSAGE).unwrap_or_default(),
+        throwable: text(&THROWABLE),
+    }
+}
+
+/// Text of an element, either a CDATA section or escaped characters.
+fn element_text(content: &str) -> String {
+    let content = content.trim();
+    match content.strip_prefix("<![CDATA[").and_then(|rest| rest.strip_suffix("]]>")) {
+        // A CDATA section can't hold "]]>", so Log4j splits it there
+        Some(cdata) => cdata.replace("]]]]><![CDATA[>", "]]>"),
+        None => unescape(content),
+    }
+}
+
+fn unescape(text: &str) -> String {
+    text.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&#10;", "\n")
+        .replace("&#13;", "\r")
+        .replace("&amp;", "&")
+}
+
+/// Prints records in the chosen format, leaving out events below `min_level`.
+#[derive(Debug, Clone, Copy)]
+pub struct LogPrinter {
+    pub format: LogFormat,
+    pub min_level: Option<LogLevel>,
+    pub is_stderr: bool,
+}
+
+impl LogPrinter {
+    pub fn print(&self, record: &LogRecord) {
+        let Some(line) = self.render(record) else {
+            return;
+        };
+        if self.is_stderr {
+            eprintln!("{}", line);
+        } else {
+            println!("{}", line);
+        }
+    }
+
+    /// The line printed for a record, or `None` when it is filtered out.
+    fn render(&self, record: &LogRecord) -> Option<String> {
+        if let (Some(min_level), Some(level)) = (self.min_level, record.level)
+            && level < min_level {
+            return None;
+        }
+        Some(match self.format {
+            LogFormat::Json => serde_json::to_string(record).expect("log records serialize"),
+            LogFormat::Text => self.format_text(record),
+        })
+    }
+
+    fn format_text(&self, record: &LogRecord) -> String {
+        let Some(level) = record.level else {
+            let prefix = if self.is_stderr { "[game/stderr]" } else { "[game]" };
+            return format!("{} {}", prefix, record.message);
+        };
+        let mut line = format!("[{}/{}]", record.thread.as_deref().unwrap_or("?"), level.as_str());
+        if let Some(logger) = &record.logger {
+            line.push_str(&format!(" [{}]", logger));
+        }
+        line.push_str(": ");
+        line.push_str(&record.message);
+        if let Some(throwable) = &record.throwable {
+            line.push('\n');
+            line.push_str(throwable.trim_end());
+        }
+        let colored = if self.is_stderr { std::io::stderr().is_terminal() } else { std::io::stdout().is_terminal() };
+        if colored && !level.color().is_empty() && std::env::var_os("NO_COLOR").is_none() {
+            format!("{}{}\x1b[0m", level.color(), line)
+        } else {
+            line
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(output: &str) -> Vec<LogRecord> {
+        let mut parser = Log4jParser::default();
+        let mut records: Vec<LogRecord> = output.lines().filter_map(|line| parser.push_line(line)).collect();
+        records.extend(parser.finish());
+        records
+    }
+
+    #[test]
+    fn cdata_event() {
+        let records = parse(concat!(
+            "<log4j:Event logger=\"net.minecraft.client.Minecraft\" timestamp=\"1697040000123\" level=\"INFO\" thread=\"Render thread\">\n",
+            "  <log4j:Message><![CDATA[Setting user: Steve]]></log4j:Message>\n",
+            "</log4j:Event>\n",
+        ));
+        assert_eq!(records.len(), 1);
+        let record = &records[0];
+        assert_eq!(record.timestamp, Some(1697040000123));
+        assert_eq!(record.level, Some(LogLevel::Info));
+        assert_eq!(record.thread.as_deref(), Some("Render thread"));
+        assert_eq!(record.logger.as_deref(), Some("net.minecraft.client.Minecraft"));
+        assert_eq!(record.message, "Setting user: Steve");
+        assert_eq!(record.throwable, None);
+    }
+
+    #[test]
+    fn multi_line_cdata_with_split_terminator() {
+        let records = parse(concat!(
+            "<log4j:Event logger=\"chat\" timestamp=\"1\" level=\"INFO\" thread=\"Server thread\">\n",
+            "  <log4j:Message><![CDATA[first line\n",
+            "second ]]]]><![CDATA[> line]]></log4j:Message>\n",
+            "</log4j:Event>\n",
+        ));
+        assert_eq!(records[0].message, "first line\nsecond ]]> line");
+    }
+
+    #[test]
+    fn throwable() {
+        let records = parse(concat!(
+            "<log4j:Event logger=\"net.minecraft.server.MinecraftServer\" timestamp=\"2\" level=\"ERROR\" thread=\"Server thread\">\n",
+            "  <log4j:Message><![CDATA[Encountered an unexpected exception]]></log4j:Message>\n",
+            "  <log4j:Throwable><![CDATA[java.lang.NullPointerException: boom\n",
+            "\tat net.minecraft.server.MinecraftServer.tick(MinecraftServer.java:100)\n",
+            "]]></log4j:Throwable>\n",
+            "</log4j:Event>\n",
+        ));
+        assert_eq!(records[0].level, Some(LogLevel::Error));
+        assert_eq!(
+            records[0].throwable.as_deref(),
+            Some("java.lang.NullPointerException: boom\n\tat net.minecraft.server.MinecraftServer.tick(MinecraftServer.java:100)\n")
+        );
+    }
+
+    #[test]
+    fn escaped_text() {
+        let records = parse(concat!(
+            "<log4j:Event logger=\"a&amp;b\" timestamp=\"3\" level=\"WARN\" thread=\"&quot;main&quot;\">\n",
+            "  <log4j:Message>&lt;Steve&gt; it&apos;s 1 &amp;&amp; 2&#10;next</log4j:Message>\n",
+            "</log4j:Event>\n",
+        ));
+        assert_eq!(records[0].logger.as_deref(), Some("a&b"));
+        assert_eq!(records[0].thread.as_deref(), Some("\"main\""));
+        assert_eq!(records[0].message, "<Steve> it's 1 && 2\nnext");
+    }
+
+    #[test]
+    fn plain_lines_pass_through() {
+        let records = parse("Picked up _JAVA_OPTIONS: -Dawt.useSystemAAFontSettings=on\n  indented <b>not xml</b>\n");
+        let messages: Vec<&str> = records.iter().map(|record| record.message.as_str()).collect();
+        assert_eq!(messages, ["Picked up _JAVA_OPTIONS: -Dawt.useSystemAAFontSettings=on", "  indented <b>not xml</b>"]);
+        assert!(records.iter().all(|record| record.level.is_none()));
+
+        // An event cut off by a crash comes out as it was printed
+        let records = parse("<log4j:Event logger=\"x\" level=\"INFO\">\n  <log4j:Message>half");
+        assert_eq!(records[0].message, "<log4j:Event logger=\"x\" level=\"INFO\">\n  <log4j:Message>half");
+    }
+
+    #[test]
+    fn printer_filters_levels() {
+        let printer = LogPrinter { format: LogFormat::Text, min_level: Some(LogLevel::Warn), is_stderr: false };
+        let mut record = LogRecord::plain("Loading 78 resource packs");
+        // Lines without a level are always shown
+        assert_eq!(printer.render(&record).as_deref(), Some("[game] Loading 78 resource packs"));
+        record.level = Some(LogLevel::Info);
+        assert_eq!(printer.render(&record), None);
+        record.level = Some(LogLevel::Error);
+        assert!(printer.render(&record).is_some());
+    }
+
+    #[test]
+    fn printer_json_lines() {
+        let printer = LogPrinter { format: LogFormat::Json, min_level: None, is_stderr: false };
+        let record = LogRecord {
+            timestamp: Some(1697040000123),
+            level: Some(LogLevel::Info),
+            thread: Some("Render thread".to_string()),
+            logger: None,
+            message: "Setting user: Steve".to_string(),
+            throwable: None,
+        };
+        assert_eq!(
+            printer.render(&record).unwrap(),
+            r#"{"timestamp":1697040000123,"level":"INFO","thread":"Render thread","message":"Setting user: Steve"}"#
+        );
+        assert_eq!(printer.render(&LogRecord::plain("hi")).unwrap(), r#"{"message":"hi"}"#);
+    }
+}
@@ -1,31 +1,172 @@
 mod cli;
+mod curseforge;
 mod error;
+mod forge;
 mod install;
+mod java;
 mod launch;
 mod launch_manager;
+mod loader;
+mod log4j;
+mod maven;
+mod mirror;
+mod modpack;
 mod models;
 mod auth;
+mod rules;
+mod version;
 mod yggdrasil;
 
 use anyhow::Result;
 use clap::Parser;
-use cli::{AuthType, Cli, Commands};
+use cli::{AuthType, Cli, Commands, InstanceCommand, JavaCommand, ModpackCommand};
 use error::LauncherError;
+use install::ListOptions;
+use launch::{LaunchOptions, QuickPlay};
+use models::Instance;
 use launch_manager::LauncherManager;
+use modpack::ExportOptions;
 use yggdrasil::{YggdrasilAccount, YggdrasilAuthenticator, YggdrasilProfile};
 
 #[tokio::main]
 async fn main() -> Result<()> {
     let cli = Cli::parse();
-    let manager = LauncherManager::new()?;
+    let mut manager = LauncherManager::new(cli.minecraft_dir.as_ref().map(std::path::PathBuf::from))?;
+    if let Some(retries) = cli.download_retries {
+        manager.installer.max_retries = retries;
+    }
+    if let Some(source) = &cli.source {
+        manager.installer.sources = source.fallback_order();
+    }
+
+    // Commands that would touch the network check for it first, so they can fall back to local files
+    let uses_network = match &cli.command {
+        Commands::List { installed, .. } => !installed,
+        Commands::Install { .. } => true,
+        Commands::Java { command: JavaCommand::Install { .. } } => true,
+        Commands::Modpack { command: ModpackCommand::Install { .. } } => true,
+        Commands::Verify { repair, .. } => *repair,
+        // External auth checks its own server when launching
+        Commands::Launch { version, .. } => version.as_ref().is_some_and(|v| v.starts_with("latest")),
+        Commands::Instance { command: InstanceCommand::Create { version, .. } } => version.starts_with("latest"),
+        _ => false,
+    };
+    let offline = cli.offline || (uses_network && !manager.installer.network_available().await);
+    if offline && !cli.offline {
+        println!("No download source is reachable, continuing in offline mode");
+    }
+    manager.installer.offline = offline;
+    manager.installer.java_path = cli.java_runtime_path.as_ref().map(std::path::PathBuf::from);
     let global_java_path = cli.java_runtime_path;
 
     match &cli.command {
-        Commands::List => {
-            manager.list_versions().await?;
+        Commands::List { version_type, installed, search } => {
+            manager.list_versions(&ListOptions {
+                version_type: version_type.clone(),
+                installed: *installed,
+                search: search.clone(),
+            }).await?;
         }
-        Commands::Install { version } => {
-            manager.install_version(&version).await?;
+        Commands::Install { version, loader, installer } => {
+            let profile_id = match (loader, installer) {
+                (Some(spec), _) => Some(manager.install_loader(version, spec).await?),
+                (None, Some(installer)) => {
+                    let version = manager.resolve_version_id(version).await?;
+                    Some(manager.install_forge(installer, Some(&version)).await?)
+                }
+                (None, None) => {
+                    manager.install_version(version).await?;
+                    None
+                }
+            };
+            if let Some(profile_id) = profile_id {
+                println!("Launch it with: mclc launch {}", profile_id);
+            }
+        }
+        Commands::Instance { command } => match command {
+            InstanceCommand::Create { name, version, game_dir, jvm_args, java_path, memory, width, height } => {
+                let version = manager.resolve_version_id(version).await?;
+                if version::load_version(&manager.installer.versions_dir, &version).is_err() {
+                    println!("Version {} is not installed yet, install it with: mclc install {}", version, version);
+                }
+                let instance = Instance {
+                    name: name.clone(),
+                    version,
+                    game_dir: game_dir.as_ref().map(std::path::absolute).transpose()?,
+                    jvm_args: jvm_args.clone(),
+                    java_path: java_path.clone(),
+                    memory: memory.clone(),
+                    width: *width,
+                    height: *height,
+                };
+                manager.save_instance(&instance)?;
+                println!("Instance {} uses {} in {}", name, instance.version, manager.instance_game_dir(&instance)?.display());
+                println!("Launch it with: mclc launch --instance {}", name);
+            }
+            InstanceCommand::List => {
+                let instances = manager.load_instances()?;
+                if instances.is_empty() {
+                    println!("No instances, create one with: mclc instance create <NAME> --version <VERSION>");
+                }
+                for instance in &instances {
+                    println!("{:<20} {:<28} {}", instance.name, instance.version, manager.instance_game_dir(instance)?.display());
+                }
+            }
+            InstanceCommand::Remove { name } => {
+                if !manager.remove_instance(name)? {
+                    eprintln!("Instance {} does not exist", name);
+                    std::process::exit(1);
+                }
+                println!("Removed instance {}, its game directory was kept", name);
+            }
+        },
+        Commands::Java { command: JavaCommand::List } => {
+            let installations = java::discover(&manager.installer.runtime_dir);
+            if installations.is_empty() {
+                println!("No Java installations found");
+            }
+            for java in &installations {
+                println!(
+                    "{:<4} {:<14} {:<8} {:<24} {}",
+                    java.major_version,
+                    java.version,
+                    java.arch.as_deref().unwrap_or("?"),
+                    java.vendor.as_deref().unwrap_or("?"),
+                    java.path.display()
+                );
+            }
+        }
+        Commands::Java { command: JavaCommand::Install { component } } => {
+            let java_path = manager.installer.install_java_runtime(component).await?;
+            println!("Java: {}", java_path.display());
+        }
+        Commands::Modpack { command: ModpackCommand::Install { file, instance } } => {
+            let record = manager.install_modpack(std::path::Path::new(file), instance).await?;
+            println!(
+                "Modpack {} {} installed into instance {} ({})",
+                record.name,
+                record.version,
+                instance,
+                manager.instance_dir(instance)?.display()
+            );
+        }
+        Commands::Modpack {
+            command: ModpackCommand::Export { instance, output, name, pack_version, version_id, include, exclude },
+        } => {
+            let output = output.clone().unwrap_or_else(|| format!("{}.mrpack", instance));
+            let (listed, overridden) = manager.export_modpack(instance, std::path::Path::new(&output), &ExportOptions {
+                name: name.clone(),
+                version: pack_version.clone(),
+                version_id: version_id.clone(),
+                include: include.clone(),
+                exclude: exclude.clone(),
+            })?;
+            println!("Exported instance {} to {} ({} downloadable file(s), {} override(s))", instance, output, listed, overridden);
+        }
+        Commands::Verify { version, repair } => {
+            if !manager.verify_version(version, *repair).await? {
+                std::process::exit(1);
+            }
         }
         Commands::Login => {
             match manager.login().await {
@@ -41,134 +182,210 @@ async fn main() -> Result<()> {
         }
         Commands::Launch {
             version,
+            instance,
             username,
             access_token,
             jvm_args,
             auth_type,
             api_url,
             authlib_jar,
-        } => match auth_type {
-            AuthType::Offline => {
-                let launch_username = username.clone().unwrap_or_else(|| "Player".to_string());
-                let launch_access_token = access_token.clone().unwrap_or_else(|| "0".to_string());
-                let launch_uuid = "00000000-0000-0000-0000-000000000000".to_string();
+            demo,
+            wait,
+            log_format,
+            log_level,
+            width,
+            height,
+            quick_play_singleplayer,
+            quick_play_multiplayer,
+            quick_play_realms,
+        } => {
+            let instance = match instance {
+                Some(name) => Some(manager.find_instance(name)?.ok_or_else(|| {
+                    anyhow::anyhow!("Instance {} does not exist, create it with: mclc instance create {} --version <VERSION>", name, name)
+                })?),
+                None => None,
+            };
+            let version = match (version, &instance) {
+                (Some(version), _) => version.clone(),
+                (None, Some(instance)) => instance.version.clone(),
+                (None, None) => unreachable!("clap requires a version or an instance"),
+            };
+            let version = &manager.resolve_version_id(&version).await?;
+            let quick_play = if let Some(world) = quick_play_singleplayer {
+                Some(QuickPlay::Singleplayer(world.clone()))
+            } else if let Some(server) = quick_play_multiplayer {
+                Some(QuickPlay::Multiplayer(server.clone()))
+            } else {
+                quick_play_realms.clone().map(QuickPlay::Realms)
+            };
+            // Command line options win over the instance's settings
+            let base_options = LaunchOptions {
+                jvm_args: jvm_args.clone().or_else(|| instance.as_ref().and_then(|i| i.jvm_args.clone())),
+                java_path: global_java_path.or_else(|| instance.as_ref().and_then(|i| i.java_path.clone())),
+                memory: instance.as_ref().and_then(|i| i.memory.clone()),
+                game_dir: instance.as_ref().map(|i| manager.instance_game_dir(i)).transpose()?,
+                demo: *demo,
+                resolution: width.zip(*height).or_else(|| instance.as_ref().and_then(|i| i.width.zip(i.height))),
+                quick_play,
+                wait: *wait,
+                log_format: log_format.unwrap_or_default(),
+                log_level: *log_level,
+                ..Default::default()
+            };
 
-                manager.launch(
-                    &version,
-                    launch_username,
-                    launch_access_token,
-                    launch_uuid,
-                    "legacy".to_string(),
-                    jvm_args.clone(),
-                    global_java_path,
-                    None,
-                    None,
-                    None,
-                )?;
-            }
-            AuthType::Msa => {
-                match manager.load_auth_cache()? {
-                    Some(auth_cache) => {
-                        manager.launch(
-                            &version,
-                            auth_cache.username,
-                            auth_cache.access_token,
-                            auth_cache.uuid,
-                            "msa".to_string(),
-                            jvm_args.clone(),
-                            global_java_path,
-                            None,
-                            None,
-                            None,
-                        )?;
-                    }
-                    None => {
-                        eprintln!("{}", LauncherError::AuthNotFound);
-                        std::process::exit(1);
+            let exit_code = match auth_type {
+                AuthType::Offline => {
+                    let launch_username = username.clone().unwrap_or_else(|| "Player".to_string());
+                    let launch_access_token = access_token.clone().unwrap_or_else(|| "0".to_string());
+                    let launch_uuid = "00000000-0000-0000-0000-000000000000".to_string();
+
+                    manager.launch(version, LaunchOptions {
+                        username: launch_username,
+                        access_token: launch_access_token,
+                        uuid: launch_uuid,
+                        user_type: "legacy".to_string(),
+                        ..base_options
+                    }).await?
+                }
+                AuthType::Msa => {
+                    match manager.load_auth_cache()? {
+                        Some(auth_cache) => {
+                            manager.launch(version, LaunchOptions {
+                                username: auth_cache.username,
+                                access_token: auth_cache.access_token,
+                                uuid: auth_cache.uuid,
+                                user_type: "msa".to_string(),
+                                ..base_options
+                            }).await?
+                        }
+                        None => {
+                            eprintln!("{}", LauncherError::AuthNotFound);
+                            std::process::exit(1);
+                        }
                     }
                 }
-            }
-            AuthType::External => {
-                let api_url = api_url.as_ref().expect("--api-url is required for external auth");
-                let Some(username) = username else {
-                    eprintln!("--username is required for external auth");
-                    std::process::exit(1);
-                };
+                AuthType::External => {
+                    let api_url = api_url.as_ref().expect("--api-url is required for external auth");
+                    let Some(username) = username else {
+                        eprintln!("--username is required for external auth");
+                        std::process::exit(1);
+                    };
 
-                // Try to find existing account
-                if let Some(account) = manager.find_account_by_identifier(&username, api_url)? {
-                    let authenticator = YggdrasilAuthenticator::new(account.api_url.clone());
+                    // Try to find existing account
+                    if let Some(account) = manager.find_account_by_identifier(username, api_url)? {
+                        let authenticator = YggdrasilAuthenticator::new(account.api_url.clone());
 
-                    // Validate the token, if expired try to refresh
-                    let account_to_use = if !authenticator.validate(&account.access_token, Some(&account.client_token)).await {
-                        println!("Token expired, refreshing...");
+                        // Whether the account can be checked depends on the auth server, not on the download sources
+                        let metadata = if offline {
+                            None
+                        } else {
+                            match authenticator.pre_fetch_metadata().await {
+                                Ok(metadata) => Some(metadata),
+                                Err(e) => {
+                                    println!("Authentication server {} is not reachable ({}), continuing offline", account.api_url, e);
+                                    None
+                                }
+                            }
+                        };
+                        let auth_offline = metadata.is_none();
 
-                        // Need to create a profile for refresh (remove dashes from UUID for the API)
-                        let profile_for_refresh = YggdrasilProfile {
-                            id: account.uuid.replace('-', ""),
-                            name: account.name.clone(),
-                            properties: None,
+                        // Validate the token, if expired try to refresh
+                        let account_to_use = if auth_offline {
+                            println!("Offline, using cached credentials for {} without validating them", account.get_display_name());
+                            account.clone()
+                        } else if !authenticator.validate(&account.access_token, Some(&account.client_token)).await {
+                            println!("Token expired, refreshing...");
+
+                            // Need to create a profile for refresh (remove dashes from UUID for the API)
+                            let profile_for_refresh = YggdrasilProfile {
+                                id: account.uuid.replace('-', ""),
+                                name: account.name.clone(),
+                                properties: None,
+                            };
+
+                            match authenticator.refresh(&account.access_token, Some(&account.client_token), Some(profile_for_refresh)).await {
+                                Ok(response) => {
+                                    let updated_account = YggdrasilAccount {
+                                        access_token: response.access_token.clone(),
+                                        client_token: response.client_token,
+                                        ..account.clone()
+                                    };
+                                    manager.save_account(&updated_account)?;
+                                    println!("Token refreshed for {}", updated_account.get_display_name());
+                                    updated_account
+                                }
+                                Err(e) => {
+                                    eprintln!("Failed to refresh token: {}", e);
+                                    eprintln!("Please login again using external-login command.");
+                                    std::process::exit(1);
+                                }
+                            }
+                        } else {
+                            println!("Using cached credentials for {}", account.get_display_name());
+                            account.clone()
                         };
 
-                        match authenticator.refresh(&account.access_token, Some(&account.client_token), Some(profile_for_refresh)).await {
-                            Ok(response) => {
-                                let updated_account = YggdrasilAccount {
-                                    access_token: response.access_token.clone(),
-                                    client_token: response.client_token,
-                                    ..account.clone()
-                                };
-                                manager.save_account(&updated_account)?;
-                                println!("Token refreshed for {}", updated_account.get_display_name());
-                                updated_account
+                        // Get authlib-injector jar path - either from provided path or auto-download
+                        let jar_path = if let Some(custom_jar_path) = authlib_jar {
+                            println!("Using custom authlib-injector: {}", custom_jar_path);
+                            std::path::PathBuf::from(custom_jar_path)
+                        } else if offline {
+                            manager.get_authlib_injector().cached().ok_or_else(|| {
+                                anyhow::anyhow!("authlib-injector has not been downloaded yet and cannot be while offline")
+                            })?
+                        } else {
+                            let authlib_injector = manager.get_authlib_injector();
+                            authlib_injector.get_or_download().await?
+                        };
+
+                        // Pre-fetched metadata, offline the copy saved by the last launch is used
+                        let prefetched = match metadata {
+                            Some(prefetched) => {
+                                if account_to_use.prefetched_metadata.as_ref() != Some(&prefetched) {
+                                    manager.save_account(&YggdrasilAccount {
+                                        prefetched_metadata: Some(prefetched.clone()),
+                                        ..account_to_use.clone()
+                                    })?;
+                                }
+                                Some(prefetched)
                             }
-                            Err(e) => {
-                                eprintln!("Failed to refresh token: {}", e);
-                                eprintln!("Please login again using external-login command.");
-                                std::process::exit(1);
+                            None => {
+                                if account_to_use.prefetched_metadata.is_none() {
+                                    eprintln!("No saved API metadata for {}, authlib-injector will fetch it itself", account_to_use.get_display_name());
+                                }
+                                account_to_use.prefetched_metadata.clone()
                             }
-                        }
-                    } else {
-                        println!("Using cached credentials for {}", account.get_display_name());
-                        account.clone()
-                    };
+                        };
 
-                    // Get authlib-injector jar path - either from provided path or auto-download
-                    let jar_path = if let Some(custom_jar_path) = authlib_jar {
-                        println!("Using custom authlib-injector: {}", custom_jar_path);
-                        std::path::PathBuf::from(custom_jar_path)
+                        manager.launch(version, LaunchOptions {
+                            username: account_to_use.name.clone(),
+                            access_token: account_to_use.access_token.clone(),
+                            uuid: account_to_use.uuid.clone(),
+                            user_type: "mojang".to_string(),
+                            authlib_injector_jar: Some(jar_path),
+                            prefetched_metadata: prefetched,
+                            api_url: Some(account_to_use.api_url.clone()),
+                            ..base_options
+                        }).await?
                     } else {
-                        let authlib_injector = manager.get_authlib_injector();
-                        authlib_injector.get_or_download().await?
-                    };
-
-                    // Pre-fetch metadata
-                    let prefetched = authenticator.pre_fetch_metadata().await?;
-
-                    manager.launch(
-                        &version,
-                        account_to_use.name.clone(),
-                        account_to_use.access_token.clone(),
-                        account_to_use.uuid.clone(),
-                        "mojang".to_string(),
-                        jvm_args.clone(),
-                        global_java_path,
-                        Some(jar_path),
-                        Some(prefetched),
-                        Some(account_to_use.api_url.clone()),
-                    )?;
-                } else {
-                    eprintln!("No cached credentials found for {} on {}. Please login first using external-login command.",
-                        username, api_url);
-                    std::process::exit(1);
+                        eprintln!("No cached credentials found for {} on {}. Please login first using external-login command.",
+                            username, api_url);
+                        std::process::exit(1);
+                    }
                 }
+            };
+            // Only set with --wait, mclc then exits the way the game did
+            if let Some(code) = exit_code
+                && code != 0 {
+                std::process::exit(code);
             }
-        },
+        }
         Commands::ExternalLogin {
             identifier,
             password,
             api_url,
         } => {
-            match manager.external_login(&identifier, &password, &api_url).await {
+            match manager.external_login(identifier, password, api_url).await {
                 Ok(_) => {
                     println!("External login successful!");
                     println!("You can now launch with: mclc launch --version <version> --auth external --api-url {} --username {}",
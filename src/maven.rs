@@ -0,0 +1,26 @@
+/// Where libraries without a repository `url` are downloaded from.
+pub const MOJANG_LIBRARIES_URL: &str = "https://libraries.minecraft.net/";
+
+/// Repository-relative path of a Maven coordinate `group:artifact:version[:classifier][@ext]`,
+/// e.g. `net.fabricmc:fabric-loader:0.16.9` -> `net/fabricmc/fabric-loader/0.16.9/fabric-loader-0.16.9.jar`.
+pub fn artifact_path(coordinate: &str) -> Option<String> {
+    let (coordinate, extension) = match coordinate.split_once('@') {
+        Some((coordinate, extension)) => (coordinate, extension),
+        None => (coordinate, "jar"),
+    };
+    let parts: Vec<&str> = coordinate.split(':').collect();
+    let (group, artifact, version, classifier) = match parts.as_slice() {
+        [group, artifact, version] => (*group, *artifact, *version, None),
+        [group, artifact, version, classifier] => (*group, *artifact, *version, Some(*classifier)),
+        _ => return None,
+    };
+    if group.is_empty() || artifact.is_empty() || version.is_empty() {
+        return None;
+    }
+
+    let file_name = match classifier {
+        Some(classifier) => format!("{}-{}-{}.{}", artifact, version, classifier, extension),
+        None => format!("{}-{}.{}", artifact, version, extension),
+    };
+    Some(format!("{}/{}/{}/{}", group.replace('.', "/"), artifact, version, file_name))
+}
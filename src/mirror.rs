@@ -0,0 +1,132 @@
+use std::fmt;
+use std::str::FromStr;
+
+const BMCLAPI_BASE: &str = "https://bmclapi2.bangbang93.com";
+
+/// Official hosts and where they live inside a BMCLAPI-style mirror.
+const MIRROR_RULES: &[(&str, &str)] = &[
+    ("https://launchermeta.mojang.com", ""),
+    ("https://launcher.mojang.com", ""),
+    ("https://piston-meta.mojang.com", ""),
+    ("https://piston-data.mojang.com", ""),
+    ("https://resources.download.minecraft.net", "/assets"),
+    ("https://libraries.minecraft.net", "/maven"),
+    ("https://repo1.maven.org/maven2", "/maven"),
+    ("https://maven.minecraftforge.net", "/maven"),
+    ("https://files.minecraftforge.net/maven", "/maven"),
+    ("https://maven.neoforged.net/releases", "/maven"),
+    ("https://maven.fabricmc.net", "/maven"),
+    ("https://meta.fabricmc.net", "/fabric-meta"),
+];
+
+/// Where files are downloaded from.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DownloadSource {
+    Official,
+    Bmclapi,
+    /// Either a base URL laid out like BMCLAPI, or a template containing `{host}` and `{path}`
+    Custom(String),
+}
+
+impl DownloadSource {
+    /// The order sources are tried in when this one is selected: itself first, then the rest.
+    pub fn fallback_order(&self) -> Vec<DownloadSource> {
+        let mut sources = vec![self.clone()];
+        for source in [DownloadSource::Official, DownloadSource::Bmclapi] {
+            if !sources.contains(&source) {
+                sources.push(source);
+            }
+        }
+        sources
+    }
+
+    /// Rewrites an official URL to this source. URLs on hosts the source doesn't mirror are returned unchanged.
+    pub fn rewrite(&self, url: &str) -> String {
+        match self {
+            DownloadSource::Official => url.to_string(),
+            DownloadSource::Bmclapi => rewrite_with_base(url, BMCLAPI_BASE),
+            DownloadSource::Custom(template) if template.contains("{host}") || template.contains("{path}") => {
+                if mirror_rule(url).is_none() {
+                    return url.to_string();
+                }
+                match url::Url::parse(url) {
+                    Ok(parsed) => template
+                        .replace("{host}", parsed.host_str().unwrap_or_default())
+                        .replace("{path}", parsed.path().trim_start_matches('/')),
+                    Err(_) => url.to_string(),
+                }
+            }
+            DownloadSource::Custom(base) => rewrite_with_base(url, base.trim_end_matches('/')),
+        }
+    }
+}
+
+fn rewrite_with_base(url: &str, base: &str) -> String {
+    match mirror_rule(url) {
+        Some((mirrored, rest)) => format!("{}{}{}", base, mirrored, rest),
+        None => url.to_string(),
+    }
+}
+
+/// The mirror path prefix for a URL on a mirrored host, and the rest of the URL after the host.
+fn mirror_rule(url: &str) -> Option<(&'static str, &str)> {
+    MIRROR_RULES.iter().find_map(|(official, mirrored)| {
+        let rest = url.strip_prefix(official)?;
+        (rest.is_empty() || rest.starts_with('/')).then_some((*mirrored, rest))
+    })
+}
+
+impl FromStr for DownloadSource {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "official" | "mojang" => Ok(DownloadSource::Official),
+            "bmclapi" => Ok(DownloadSource::Bmclapi),
+            custom if custom.starts_with("http://") || custom.starts_with("https://") => {
+                Ok(DownloadSource::Custom(custom.to_string()))
+            }
+            other => Err(format!(
+                "unknown download source '{}', expected 'official', 'bmclapi' or a mirror URL",
+                other
+            )),
+        }
+    }
+}
+
+impl fmt::Display for DownloadSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DownloadSource::Official => write!(f, "official"),
+            DownloadSource::Bmclapi => write!(f, "bmclapi"),
+            DownloadSource::Custom(url) => write!(f, "{}", url),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rewrite() {
+        let template = DownloadSource::Custom("https://mirror.example/{host}/{path}".to_string());
+        let base = DownloadSource::Custom("https://mirror.example/".to_string());
+        let cases: &[(&DownloadSource, &str, &str)] = &[
+            (&DownloadSource::Official, "https://libraries.minecraft.net/a/b.jar", "https://libraries.minecraft.net/a/b.jar"),
+            (&DownloadSource::Bmclapi, "https://libraries.minecraft.net/a/b.jar", "https://bmclapi2.bangbang93.com/maven/a/b.jar"),
+            (&DownloadSource::Bmclapi, "https://resources.download.minecraft.net/ab/abcd", "https://bmclapi2.bangbang93.com/assets/ab/abcd"),
+            (&base, "https://piston-meta.mojang.com/mc/game/version_manifest_v2.json", "https://mirror.example/mc/game/version_manifest_v2.json"),
+            (&base, "https://cdn.modrinth.com/data/x/y.jar", "https://cdn.modrinth.com/data/x/y.jar"),
+            (&template, "https://libraries.minecraft.net/a/b.jar", "https://mirror.example/libraries.minecraft.net/a/b.jar"),
+            (&template, "https://cdn.modrinth.com/data/x/y.jar", "https://cdn.modrinth.com/data/x/y.jar"),
+            (&template, "https://edge.forgecdn.net/files/1/2/a.jar", "https://edge.forgecdn.net/files/1/2/a.jar"),
+            (&template, "https://meta.quiltmc.org/v3/versions/loader", "https://meta.quiltmc.org/v3/versions/loader"),
+            // Only whole host names match
+            (&template, "https://libraries.minecraft.net.evil/a.jar", "https://libraries.minecraft.net.evil/a.jar"),
+        ];
+        for (source, url, expected) in cases {
+            assert_eq!(source.rewrite(url), *expected, "{} via {}", url, source);
+        }
+    }
+}
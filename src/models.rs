@@ -1,5 +1,39 @@
 use serde::{Deserialize, Serialize};
 
+// Launcher configuration (config.json in the config directory)
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LauncherConfig {
+    /// Minecraft directory used when neither --minecraft-dir nor MCLC_HOME is given
+    pub minecraft_dir: Option<String>,
+    /// Download source: "official", "bmclapi" or a mirror URL
+    pub source: Option<String>,
+    /// Base URL of the Fabric meta API
+    pub fabric_meta_url: Option<String>,
+    /// Base URL of the Quilt meta API
+    pub quilt_meta_url: Option<String>,
+    /// Base URL of the CurseForge API
+    pub curseforge_api_url: Option<String>,
+    /// Key sent as `x-api-key` to the CurseForge API
+    pub curseforge_api_key: Option<String>,
+}
+
+// A named instance (instances.json in the config directory)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Instance {
+    pub name: String,
+    /// Version the instance launches
+    pub version: String,
+    /// Defaults to instances/<name> in the Minecraft directory
+    pub game_dir: Option<std::path::PathBuf>,
+    pub jvm_args: Option<String>,
+    pub java_path: Option<String>,
+    /// Maximum heap size, e.g. "4G"
+    pub memory: Option<String>,
+    pub width: Option<u32>,
+    pub height: Option<u32>,
+}
+
 // Authentication models
 #[derive(Debug, Serialize, Deserialize)]
 pub struct AuthCache {
@@ -92,15 +126,221 @@ pub struct MinecraftProfile {
 // Version models
 #[derive(Debug, Deserialize, Serialize)]
 pub struct VersionManifest {
+    pub latest: LatestVersions,
     pub versions: Vec<VersionInfo>,
 }
 
+impl VersionManifest {
+    /// Finds a version by id, also accepting the `latest` and `latest-snapshot` aliases.
+    pub fn find(&self, version_id: &str) -> Option<&VersionInfo> {
+        let version_id = match version_id {
+            "latest" => &self.latest.release,
+            "latest-snapshot" => &self.latest.snapshot,
+            other => other,
+        };
+        self.versions.iter().find(|v| v.id == version_id)
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct LatestVersions {
+    pub release: String,
+    pub snapshot: String,
+}
+
 #[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct VersionInfo {
     pub id: String,
     #[serde(rename = "type")]
     pub version_type: String,
     pub url: String,
+    #[serde(rename = "releaseTime")]
+    pub release_time: String,
+    /// SHA-1 of the version JSON at `url`
+    pub sha1: String,
+}
+
+// Loader meta API models (one entry of `/versions/loader/<game version>`)
+#[derive(Debug, Deserialize)]
+pub struct LoaderEntry {
+    pub loader: LoaderVersion,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct LoaderVersion {
+    pub version: String,
+    /// Only published by Fabric; Quilt marks pre-releases in the version instead
+    #[serde(default)]
+    pub stable: bool,
+}
+
+// Forge/NeoForge installer install_profile.json (spec 1, Minecraft 1.13+)
+#[derive(Debug, Deserialize)]
+pub struct InstallProfile {
+    /// Minecraft version the installer is for
+    pub minecraft: String,
+    /// Path of the version JSON inside the installer
+    pub json: String,
+    #[serde(default)]
+    pub data: HashMap<String, SidedData>,
+    #[serde(default)]
+    pub processors: Vec<Processor>,
+    /// Libraries the processors need
+    #[serde(default)]
+    pub libraries: Vec<Library>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct SidedData {
+    #[serde(default)]
+    pub client: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct Processor {
+    /// Maven coordinate of the processor jar
+    pub jar: String,
+    #[serde(default)]
+    pub classpath: Vec<String>,
+    #[serde(default)]
+    pub args: Vec<String>,
+    /// Output path -> expected SHA-1, both with data placeholders
+    #[serde(default)]
+    pub outputs: HashMap<String, String>,
+    /// "client" and/or "server"; all sides when absent
+    pub sides: Option<Vec<String>>,
+}
+
+// Modrinth modpack index (modrinth.index.json in a .mrpack)
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ModrinthIndex {
+    pub format_version: u32,
+    pub game: String,
+    pub version_id: String,
+    pub name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub summary: Option<String>,
+    pub files: Vec<ModrinthFile>,
+    /// "minecraft" plus at most one of "fabric-loader", "quilt-loader", "forge" and "neoforge"
+    pub dependencies: BTreeMap<String, String>,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ModrinthFile {
+    /// Destination relative to the game directory
+    pub path: String,
+    /// "sha1" and "sha512"
+    pub hashes: BTreeMap<String, String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub env: Option<ModrinthEnv>,
+    pub downloads: Vec<String>,
+    pub file_size: Option<u64>,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct ModrinthEnv {
+    /// "required", "optional" or "unsupported"
+    pub client: String,
+    pub server: String,
+}
+
+// CurseForge modpack manifest (manifest.json in a CurseForge export)
+#[derive(Debug, Deserialize)]
+pub struct CurseForgeManifest {
+    pub minecraft: CurseForgeMinecraft,
+    pub name: String,
+    #[serde(default)]
+    pub version: String,
+    pub files: Vec<CurseForgeManifestFile>,
+    /// Directory of the overrides inside the zip
+    pub overrides: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CurseForgeMinecraft {
+    pub version: String,
+    #[serde(default)]
+    pub mod_loaders: Vec<CurseForgeModLoader>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CurseForgeModLoader {
+    /// Loader and version, e.g. "forge-47.2.0" or "fabric-0.16.9"
+    pub id: String,
+    #[serde(default)]
+    pub primary: bool,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CurseForgeManifestFile {
+    #[serde(rename = "projectID")]
+    pub project_id: u64,
+    #[serde(rename = "fileID")]
+    pub file_id: u64,
+    #[serde(default = "default_true")]
+    pub required: bool,
+}
+
+fn default_true() -> bool {
+    true
+}
+
+// CurseForge API models (`/v1/mods/<project>/files/<file>`)
+#[derive(Debug, Deserialize)]
+pub struct CurseForgeResponse<T> {
+    pub data: T,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CurseForgeFile {
+    pub id: u64,
+    pub file_name: String,
+    /// Missing when the author does not allow third-party downloads
+    pub download_url: Option<String>,
+    #[serde(default)]
+    pub hashes: Vec<CurseForgeHash>,
+    pub file_length: Option<u64>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CurseForgeHash {
+    pub value: String,
+    /// 1 is SHA-1, 2 is MD5
+    pub algo: u32,
+}
+
+// What `mclc modpack install` put into an instance (mclc-modpack.json in its game directory)
+#[derive(Debug, Deserialize, Serialize)]
+pub struct ModpackRecord {
+    pub name: String,
+    pub version: String,
+    pub summary: Option<String>,
+    /// Same keys as the dependencies of a Modrinth pack
+    pub dependencies: BTreeMap<String, String>,
+    /// Version the instance is launched with
+    pub version_id: String,
+    /// Files downloaded for the pack, so an update can remove the ones it drops
+    pub files: Vec<ModpackFile>,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct ModpackFile {
+    pub path: String,
+    pub sha1: Option<String>,
+    pub sha512: Option<String>,
+    pub url: Option<String>,
+    pub size: Option<u64>,
+}
+
+// Validators of the cached version manifest, sent back when refreshing it
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct ManifestCacheMeta {
+    pub etag: Option<String>,
+    pub last_modified: Option<String>,
 }
 
 #[derive(Debug, Deserialize, Serialize)]
@@ -108,9 +348,14 @@ pub struct VersionDetails {
     pub id: String,
     #[serde(rename = "type")]
     pub version_type: String,
+    #[serde(rename = "inheritsFrom")]
+    pub inherits_from: Option<String>,
+    /// Version whose client JAR is used, when not this one
+    pub jar: Option<String>,
     pub downloads: Option<Downloads>,
+    #[serde(default)]
     pub libraries: Vec<Library>,
-    #[serde(rename = "mainClass")]
+    #[serde(rename = "mainClass", default)]
     pub main_class: String,
     #[serde(rename = "minecraftArguments")]
     pub minecraft_arguments: Option<String>,
@@ -119,14 +364,89 @@ pub struct VersionDetails {
     pub asset_index: Option<AssetIndex>,
     #[serde(rename = "javaVersion")]
     pub java_version: Option<JavaVersionSpec>,
+    pub logging: Option<Logging>,
+    #[serde(rename = "releaseTime")]
+    pub release_time: Option<String>,
+}
+
+impl VersionDetails {
+    /// Id of the version directory holding the client JAR.
+    pub fn jar_id(&self) -> &str {
+        self.jar.as_deref().unwrap_or(&self.id)
+    }
 }
 
 #[derive(Debug, Deserialize, Serialize)]
 pub struct JavaVersionSpec {
+    /// Mojang runtime the official launcher would use, e.g. `java-runtime-delta`
+    pub component: Option<String>,
     #[serde(rename = "majorVersion")]
     pub major_version: u32,
 }
 
+#[derive(Debug, Deserialize, Serialize)]
+pub struct Logging {
+    pub client: Option<LoggingConfig>,
+}
+
+/// Log4j config that makes the game print its log as XML events.
+#[derive(Debug, Deserialize, Serialize)]
+pub struct LoggingConfig {
+    /// JVM argument with a `${path}` placeholder for the config file
+    pub argument: String,
+    pub file: LoggingFile,
+    #[serde(rename = "type")]
+    pub config_type: Option<String>,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct LoggingFile {
+    pub id: String,
+    pub sha1: String,
+    pub size: u64,
+    pub url: String,
+}
+
+/// Mojang's Java runtime index: platform, then component, then its builds (normally one).
+pub type JavaRuntimeIndex = HashMap<String, HashMap<String, Vec<JavaRuntimeBuild>>>;
+
+#[derive(Debug, Deserialize)]
+pub struct JavaRuntimeBuild {
+    pub manifest: DownloadInfo,
+    pub version: JavaRuntimeVersion,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct JavaRuntimeVersion {
+    pub name: String,
+}
+
+/// Every file of a Java runtime build, keyed by its path inside the runtime.
+#[derive(Debug, Deserialize)]
+pub struct JavaRuntimeManifest {
+    pub files: BTreeMap<String, JavaRuntimeFile>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum JavaRuntimeFile {
+    Directory,
+    File {
+        #[serde(default)]
+        executable: bool,
+        downloads: JavaRuntimeDownloads,
+    },
+    Link {
+        target: String,
+    },
+}
+
+#[derive(Debug, Deserialize)]
+pub struct JavaRuntimeDownloads {
+    /// Uncompressed file; the `lzma` variant is not used
+    pub raw: DownloadInfo,
+}
+
 #[derive(Debug, Deserialize, Serialize)]
 pub struct AssetIndex {
     pub id: String,
@@ -151,21 +471,38 @@ pub struct DownloadInfo {
 pub struct Library {
     pub name: String,
     pub downloads: Option<LibraryDownloads>,
+    /// Maven repository for libraries without `downloads` (Mojang's when absent)
+    pub url: Option<String>,
+    pub sha1: Option<String>,
+    pub size: Option<u64>,
     pub rules: Option<Vec<Rule>>,
     pub natives: Option<HashMap<String, String>>,
 }
 
-use std::collections::HashMap;
+impl Library {
+    /// Path of the main artifact under `libraries/`, taken from `downloads` or else derived from the Maven coordinate.
+    pub fn artifact_path(&self) -> Option<String> {
+        match &self.downloads {
+            Some(downloads) => downloads.artifact.as_ref().map(|artifact| artifact.path.clone()),
+            None => crate::maven::artifact_path(&self.name),
+        }
+    }
+}
+
+use std::collections::{BTreeMap, HashMap};
 
 #[derive(Debug, Deserialize, Serialize)]
 pub struct Rule {
     pub action: String,
     pub os: Option<OsRule>,
+    pub features: Option<HashMap<String, bool>>,
 }
 
 #[derive(Debug, Deserialize, Serialize)]
 pub struct OsRule {
-    pub name: String,
+    pub name: Option<String>,
+    pub version: Option<String>,
+    pub arch: Option<String>,
 }
 
 #[derive(Debug, Deserialize, Serialize)]
@@ -195,10 +532,56 @@ pub struct Classifiers {
     pub other: HashMap<String, Artifact>,
 }
 
+impl Classifiers {
+    pub fn get(&self, classifier: &str) -> Option<&Artifact> {
+        let named = match classifier {
+            "natives-linux" => self.natives_linux.as_ref(),
+            "natives-windows" => self.natives_windows.as_ref(),
+            "natives-macos" => self.natives_macos.as_ref(),
+            _ => None,
+        };
+        named.or_else(|| self.other.get(classifier))
+    }
+}
+
 #[derive(Debug, Deserialize, Serialize)]
 pub struct Arguments {
-    pub game: Vec<serde_json::Value>,
-    pub jvm: Vec<serde_json::Value>,
+    #[serde(default)]
+    pub game: Vec<Argument>,
+    #[serde(default)]
+    pub jvm: Vec<Argument>,
+}
+
+/// An entry of `arguments.game` / `arguments.jvm`: either a plain string or a
+/// rule-guarded object whose `value` is a single string or a list of strings.
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(untagged)]
+pub enum Argument {
+    Plain(String),
+    Conditional {
+        rules: Vec<Rule>,
+        value: ArgumentValue,
+    },
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(untagged)]
+pub enum ArgumentValue {
+    Single(String),
+    Multiple(Vec<String>),
+}
+
+impl Argument {
+    /// Whether any of the values this argument can expand to contains `text`.
+    pub fn mentions(&self, text: &str) -> bool {
+        match self {
+            Argument::Plain(value) => value.contains(text),
+            Argument::Conditional { value: ArgumentValue::Single(value), .. } => value.contains(text),
+            Argument::Conditional { value: ArgumentValue::Multiple(values), .. } => {
+                values.iter().any(|v| v.contains(text))
+            }
+        }
+    }
 }
 
 #[derive(Debug, Deserialize, Serialize)]
@@ -0,0 +1,639 @@
+use crate::curseforge::CurseForgeApi;
+use crate::install::{DownloadTask, Installer};
+use crate::loader::{Loader, LoaderSpec};
+use crate::models::{CurseForgeFile, CurseForgeManifest, ModpackFile, ModpackRecord, ModrinthFile, ModrinthIndex};
+use crate::version;
+use anyhow::Context;
+use futures_util::stream::{self, StreamExt};
+use globset::{Glob, GlobSet, GlobSetBuilder};
+use reqwest::Client;
+use serde::de::DeserializeOwned;
+use sha2::{Digest, Sha512};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fs::{self, File};
+use std::io::Write;
+use std::path::{Component, Path, PathBuf};
+
+pub const MRPACK_INDEX_FILE: &str = "modrinth.index.json";
+pub const CURSEFORGE_MANIFEST_FILE: &str = "manifest.json";
+/// Written to the game directory of an instance a pack was installed into.
+pub const MODPACK_RECORD_FILE: &str = "mclc-modpack.json";
+const MAX_CONCURRENT_LOOKUPS: usize = 8;
+/// Left out of exports unless an include glob names them.
+const DEFAULT_EXPORT_EXCLUDES: &[&str] = &["logs/**", "crash-reports/**", "saves/**", "screenshots/**", "*.log"];
+/// Hosts Modrinth accepts in `downloads` of a published pack.
+const MRPACK_DOWNLOAD_HOSTS: &[&str] = &["cdn.modrinth.com", "github.com", "raw.githubusercontent.com", "gitlab.com"];
+
+/// Installs a Modrinth `.mrpack` or a CurseForge export into `game_dir`, telling them apart by their index file.
+/// Files a previous version of the pack installed but this one no longer lists are removed.
+pub async fn install_modpack(
+    installer: &Installer,
+    curseforge: &CurseForgeApi,
+    pack_path: &Path,
+    game_dir: &Path,
+) -> anyhow::Result<ModpackRecord> {
+    let file = File::open(pack_path).with_context(|| format!("Failed to open {:?}", pack_path))?;
+    let mut archive = zip::ZipArchive::new(file).with_context(|| format!("{:?} is not a modpack archive", pack_path))?;
+    if archive.by_name(MRPACK_INDEX_FILE).is_ok() {
+        install_mrpack(installer, archive, game_dir).await
+    } else if archive.by_name(CURSEFORGE_MANIFEST_FILE).is_ok() {
+        install_curseforge(installer, curseforge, archive, game_dir).await
+    } else {
+        anyhow::bail!("{:?} has neither a {} nor a {}", pack_path, MRPACK_INDEX_FILE, CURSEFORGE_MANIFEST_FILE)
+    }
+}
+
+/// Modrinth pack: its Minecraft version and loader, every file the client needs (optional ones included)
+/// and the overrides.
+async fn install_mrpack(installer: &Installer, mut archive: zip::ZipArchive<File>, game_dir: &Path) -> anyhow::Result<ModpackRecord> {
+    let index: ModrinthIndex = read_json(&mut archive, MRPACK_INDEX_FILE)?;
+    if index.game != "minecraft" {
+        anyhow::bail!("{} is a pack for {}, not Minecraft", index.name, index.game);
+    }
+    if index.format_version != 1 {
+        anyhow::bail!("Unsupported modpack format version {}", index.format_version);
+    }
+    println!("Installing modpack {} {}", index.name, index.version_id);
+
+    let mut files = Vec::new();
+    for file in &index.files {
+        if file.env.as_ref().is_some_and(|env| env.client == "unsupported") {
+            continue;
+        }
+        // Further URLs are mirrors of the first
+        let url = file
+            .downloads
+            .first()
+            .with_context(|| format!("{} has no download URL", file.path))?;
+        files.push(ModpackFile {
+            path: file.path.clone(),
+            sha1: file.hashes.get("sha1").cloned(),
+            sha512: file.hashes.get("sha512").cloned(),
+            url: Some(url.clone()),
+            size: file.file_size,
+        });
+    }
+
+    let record = ModpackRecord {
+        name: index.name,
+        version: index.version_id,
+        summary: index.summary,
+        dependencies: index.dependencies,
+        version_id: String::new(),
+        files,
+    };
+    // Client overrides are applied last so they win over the common ones
+    finish_install(installer, archive, &["overrides", "client-overrides"], game_dir, record).await
+}
+
+/// CurseForge export: the loader from `minecraft.modLoaders`, the required files resolved through the API
+/// into `mods/`, and the overrides.
+async fn install_curseforge(
+    installer: &Installer,
+    curseforge: &CurseForgeApi,
+    mut archive: zip::ZipArchive<File>,
+    game_dir: &Path,
+) -> anyhow::Result<ModpackRecord> {
+    let manifest: CurseForgeManifest = read_json(&mut archive, CURSEFORGE_MANIFEST_FILE)?;
+    println!("Installing modpack {} {}", manifest.name, manifest.version);
+
+    let mut dependencies = BTreeMap::from([("minecraft".to_string(), manifest.minecraft.version.clone())]);
+    let mod_loader = manifest
+        .minecraft
+        .mod_loaders
+        .iter()
+        .find(|loader| loader.primary)
+        .or(manifest.minecraft.mod_loaders.first());
+    if let Some(mod_loader) = mod_loader {
+        let (name, version) = mod_loader
+            .id
+            .split_once('-')
+            .with_context(|| format!("Unrecognized mod loader {}", mod_loader.id))?;
+        let spec: LoaderSpec = format!("{}@{}", name, version)
+            .parse()
+            .map_err(|e| anyhow::anyhow!("Unrecognized mod loader {}: {}", mod_loader.id, e))?;
+        dependencies.insert(spec.loader.mrpack_dependency().to_string(), version.to_string());
+    }
+
+    if installer.offline {
+        anyhow::bail!("CurseForge files cannot be looked up while offline");
+    }
+    let required: Vec<_> = manifest.files.iter().filter(|file| file.required).collect();
+    let skipped = manifest.files.len() - required.len();
+    if skipped > 0 {
+        println!("Skipping {} optional file(s)", skipped);
+    }
+    println!("Looking up {} file(s) on CurseForge...", required.len());
+    let client = Client::new();
+    let resolved: Vec<anyhow::Result<CurseForgeFile>> = stream::iter(&required)
+        .map(|file| curseforge.file(&client, file.project_id, file.file_id))
+        .buffered(MAX_CONCURRENT_LOOKUPS)
+        .collect()
+        .await;
+
+    let mut files = Vec::new();
+    // Authors can opt out of third-party downloads, those files have to be fetched by hand
+    let mut manual = Vec::new();
+    for (entry, result) in required.iter().zip(resolved) {
+        let file = result?;
+        files.push(ModpackFile {
+            path: format!("mods/{}", file.file_name),
+            sha1: file.sha1().map(str::to_string),
+            sha512: None,
+            url: file.download_url.clone(),
+            size: file.file_length,
+        });
+        if file.download_url.is_none() {
+            manual.push((entry.project_id, file));
+        }
+    }
+
+    let record = ModpackRecord {
+        name: manifest.name,
+        version: manifest.version,
+        summary: None,
+        dependencies,
+        version_id: String::new(),
+        files,
+    };
+    let overrides = manifest.overrides.as_deref().unwrap_or("overrides");
+    let record = finish_install(installer, archive, &[overrides], game_dir, record).await?;
+    if !manual.is_empty() {
+        eprintln!(
+            "{} file(s) cannot be downloaded by third-party launchers, download them from CurseForge into {}:",
+            manual.len(),
+            game_dir.join("mods").display()
+        );
+        for (project_id, file) in &manual {
+            eprintln!("  {} (project {}, file {})", file.file_name, project_id, file.id);
+        }
+    }
+    Ok(record)
+}
+
+/// Installs the pack's dependencies, downloads its files, applies the overrides and saves the record.
+async fn finish_install(
+    installer: &Installer,
+    mut archive: zip::ZipArchive<File>,
+    overrides: &[&str],
+    game_dir: &Path,
+    mut record: ModpackRecord,
+) -> anyhow::Result<ModpackRecord> {
+    let mut tasks = Vec::new();
+    for file in &record.files {
+        let path = game_dir.join(safe_relative_path(&file.path)?);
+        // Files without a URL are left for the user to download
+        if let Some(url) = &file.url {
+            tasks.push(DownloadTask::new(url.clone(), path, "modpack", file.sha1.clone(), file.size));
+        }
+    }
+
+    record.version_id = install_dependencies(installer, &record.dependencies).await?;
+    // Files already in place are only kept when their SHA-512 matches as well, the rest are downloaded again
+    for file in &record.files {
+        let path = game_dir.join(&file.path);
+        if let Some(expected) = &file.sha512
+            && path.is_file()
+            && !sha512_file(&path)?.eq_ignore_ascii_case(expected) {
+            println!("{} is outdated or corrupt, downloading it again", file.path);
+            fs::remove_file(&path)?;
+        }
+    }
+    println!("Downloading {} modpack file(s)...", tasks.len());
+    installer.download_files(tasks).await?;
+    for file in &record.files {
+        if let Some(expected) = &file.sha512 {
+            let path = game_dir.join(&file.path);
+            let actual = sha512_file(&path)?;
+            if !actual.eq_ignore_ascii_case(expected) {
+                fs::remove_file(&path)?;
+                anyhow::bail!("SHA-512 mismatch for {}: expected {}, got {}", path.display(), expected, actual);
+            }
+        }
+    }
+
+    remove_dropped_files(game_dir, &record.files)?;
+    for prefix in overrides {
+        extract_overrides(&mut archive, prefix, game_dir)?;
+    }
+    fs::write(game_dir.join(MODPACK_RECORD_FILE), serde_json::to_string_pretty(&record)?)?;
+    Ok(record)
+}
+
+/// Installs the Minecraft version and the loader named in Modrinth-style `dependencies`
+/// and returns the id of the version to launch.
+pub async fn install_dependencies(installer: &Installer, dependencies: &BTreeMap<String, String>) -> anyhow::Result<String> {
+    let minecraft = dependencies
+        .get("minecraft")
+        .context("The modpack does not name a Minecraft version")?;
+    for name in dependencies.keys() {
+        if name != "minecraft" && !Loader::ALL.iter().any(|loader| loader.mrpack_dependency() == name) {
+            eprintln!("Ignoring unknown modpack dependency {}", name);
+        }
+    }
+
+    let loader = Loader::ALL.iter().find_map(|loader| {
+        dependencies.get(loader.mrpack_dependency()).map(|version| LoaderSpec {
+            loader: *loader,
+            version: Some(version.clone()),
+        })
+    });
+    match loader {
+        Some(spec) => installer.install_loader(minecraft, &spec).await,
+        None => {
+            installer.install_version(minecraft).await?;
+            Ok(minecraft.clone())
+        }
+    }
+}
+
+/// What goes into an exported pack besides the files.
+#[derive(Debug, Clone, Default)]
+pub struct ExportOptions {
+    /// Defaults to the installed pack's name, then the instance name
+    pub name: Option<String>,
+    /// Defaults to the installed pack's version, then 1.0.0
+    pub version: Option<String>,
+    /// Version the instance runs, for the Minecraft and loader dependencies (defaults to the installed pack's)
+    pub version_id: Option<String>,
+    /// Only files matching one of these globs are exported
+    pub include: Vec<String>,
+    /// Files matching one of these globs are left out
+    pub exclude: Vec<String>,
+}
+
+/// Writes the instance in `game_dir` to `output` as a Modrinth pack. Files the installed pack's record
+/// still matches are listed by hash and download URL, everything else goes into `overrides/`.
+/// Returns how many files were listed and how many went into the overrides.
+pub fn export_mrpack(
+    versions_dir: &Path,
+    instance: &str,
+    game_dir: &Path,
+    output: &Path,
+    options: &ExportOptions,
+) -> anyhow::Result<(usize, usize)> {
+    let record = load_record(game_dir)?;
+    let dependencies = match (&options.version_id, &record) {
+        (Some(version_id), _) => version_dependencies(versions_dir, version_id)?,
+        (None, Some(record)) => record.dependencies.clone(),
+        (None, None) => anyhow::bail!("Instance {} was not installed from a modpack, pass --version-id", instance),
+    };
+
+    let include = glob_set(&options.include)?;
+    let exclude = glob_set(&options.exclude)?;
+    let default_exclude = glob_set(DEFAULT_EXPORT_EXCLUDES)?;
+    let output = std::path::absolute(output)?;
+    let mut paths = Vec::new();
+    walk_files(game_dir, game_dir, &mut paths)?;
+    paths.sort();
+    paths.retain(|path| {
+        let included = include.is_match(path);
+        path != MODPACK_RECORD_FILE
+            && game_dir.join(path) != output
+            && (included || (options.include.is_empty() && !default_exclude.is_match(path)))
+            && !exclude.is_match(path)
+    });
+
+    let recorded: HashMap<&str, &ModpackFile> = record
+        .iter()
+        .flat_map(|record| &record.files)
+        .map(|file| (file.path.as_str(), file))
+        .collect();
+    let mut files = Vec::new();
+    let mut overrides = Vec::new();
+    for path in paths {
+        let full_path = game_dir.join(&path);
+        let sha1 = Installer::sha1_file(&full_path)?;
+        // A recorded file only stays downloadable while it is unchanged, and only from a host Modrinth accepts
+        match recorded.get(path.as_str()) {
+            Some(ModpackFile { sha1: Some(recorded_sha1), url: Some(url), .. })
+                if recorded_sha1.eq_ignore_ascii_case(&sha1) && mrpack_download_allowed(url) => {
+                files.push(ModrinthFile {
+                    hashes: BTreeMap::from([("sha1".to_string(), sha1), ("sha512".to_string(), sha512_file(&full_path)?)]),
+                    env: None,
+                    downloads: vec![url.clone()],
+                    file_size: Some(fs::metadata(&full_path)?.len()),
+                    path,
+                });
+            }
+            _ => overrides.push(path),
+        }
+    }
+
+    let index = ModrinthIndex {
+        format_version: 1,
+        game: "minecraft".to_string(),
+        version_id: options
+            .version
+            .clone()
+            .or_else(|| record.as_ref().map(|record| record.version.clone()))
+            .unwrap_or_else(|| "1.0.0".to_string()),
+        name: options
+            .name
+            .clone()
+            .or_else(|| record.as_ref().map(|record| record.name.clone()))
+            .unwrap_or_else(|| instance.to_string()),
+        summary: record.as_ref().and_then(|record| record.summary.clone()),
+        files,
+        dependencies,
+    };
+
+    let mut zip = zip::ZipWriter::new(File::create(&output).with_context(|| format!("Failed to create {:?}", output))?);
+    let file_options = zip::write::FileOptions::default().compression_method(zip::CompressionMethod::Deflated);
+    zip.start_file(MRPACK_INDEX_FILE, file_options)?;
+    zip.write_all(serde_json::to_string_pretty(&index)?.as_bytes())?;
+    for path in &overrides {
+        zip.start_file(format!("overrides/{}", path), file_options)?;
+        let mut file = File::open(game_dir.join(path)).with_context(|| format!("Failed to open {}", path))?;
+        std::io::copy(&mut file, &mut zip)?;
+    }
+    zip.finish()?;
+    Ok((index.files.len(), overrides.len()))
+}
+
+fn mrpack_download_allowed(url: &str) -> bool {
+    url::Url::parse(url).is_ok_and(|url| {
+        url.scheme() == "https" && url.host_str().is_some_and(|host| MRPACK_DOWNLOAD_HOSTS.contains(&host))
+    })
+}
+
+/// Modrinth-style dependencies of an installed version: the Minecraft version whose client it runs
+/// on and the loader found among its libraries.
+fn version_dependencies(versions_dir: &Path, version_id: &str) -> anyhow::Result<BTreeMap<String, String>> {
+    let details = version::load_version(versions_dir, version_id)?;
+    let mut dependencies = BTreeMap::from([("minecraft".to_string(), details.jar_id().to_string())]);
+    if let Some((loader, loader_version)) = details.libraries.iter().find_map(|library| Loader::from_library(&library.name)) {
+        dependencies.insert(loader.mrpack_dependency().to_string(), loader_version);
+    }
+    Ok(dependencies)
+}
+
+fn glob_set<S: AsRef<str>>(patterns: &[S]) -> anyhow::Result<GlobSet> {
+    let mut builder = GlobSetBuilder::new();
+    for pattern in patterns {
+        let pattern = pattern.as_ref();
+        builder.add(Glob::new(pattern).with_context(|| format!("Invalid glob {}", pattern))?);
+    }
+    Ok(builder.build()?)
+}
+
+/// Paths of all files under `dir`, relative to `root` and separated by `/` as in a pack.
+fn walk_files(dir: &Path, root: &Path, paths: &mut Vec<String>) -> anyhow::Result<()> {
+    for entry in fs::read_dir(dir).with_context(|| format!("Failed to read {:?}", dir))? {
+        let path = entry?.path();
+        if path.is_dir() {
+            walk_files(&path, root, paths)?;
+        } else if let Ok(relative) = path.strip_prefix(root) {
+            let parts: Vec<_> = relative.components().map(|c| c.as_os_str().to_string_lossy()).collect();
+            paths.push(parts.join("/"));
+        }
+    }
+    Ok(())
+}
+
+/// The record of the pack installed in `game_dir`, if any.
+pub fn load_record(game_dir: &Path) -> anyhow::Result<Option<ModpackRecord>> {
+    let record_path = game_dir.join(MODPACK_RECORD_FILE);
+    if !record_path.exists() {
+        return Ok(None);
+    }
+    let json = fs::read_to_string(&record_path)?;
+    let record = serde_json::from_str(&json).with_context(|| format!("Failed to parse {:?}", record_path))?;
+    Ok(Some(record))
+}
+
+/// Deletes the files the previously installed pack listed that `files` no longer does.
+fn remove_dropped_files(game_dir: &Path, files: &[ModpackFile]) -> anyhow::Result<()> {
+    let Some(previous) = load_record(game_dir)? else {
+        return Ok(());
+    };
+    let kept: HashSet<&str> = files.iter().map(|file| file.path.as_str()).collect();
+    for file in previous.files.iter().filter(|file| !kept.contains(file.path.as_str())) {
+        let path = game_dir.join(safe_relative_path(&file.path)?);
+        if path.exists() {
+            println!("Removing {}, no longer part of the pack", file.path);
+            fs::remove_file(&path)?;
+        }
+    }
+    Ok(())
+}
+
+/// Copies the entries under `prefix/` in the pack into `game_dir`.
+fn extract_overrides(archive: &mut zip::ZipArchive<File>, prefix: &str, game_dir: &Path) -> anyhow::Result<()> {
+    for i in 0..archive.len() {
+        let mut entry = archive.by_index(i)?;
+        let Some(relative) = entry.enclosed_name().and_then(|p| p.strip_prefix(prefix).ok().map(Path::to_path_buf)) else {
+            continue;
+        };
+        if entry.is_dir() || relative.as_os_str().is_empty() {
+            continue;
+        }
+        let out_path = game_dir.join(relative);
+        if let Some(parent) = out_path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let mut out = File::create(&out_path).with_context(|| format!("Failed to create {:?}", out_path))?;
+        std::io::copy(&mut entry, &mut out)?;
+    }
+    Ok(())
+}
+
+/// A pack-relative path that stays inside the game directory.
+fn safe_relative_path(path: &str) -> anyhow::Result<PathBuf> {
+    let relative = PathBuf::from(path);
+    if relative.as_os_str().is_empty() || !relative.components().all(|c| matches!(c, Component::Normal(_))) {
+        anyhow::bail!("Refusing to write {} outside the game directory", path);
+    }
+    Ok(relative)
+}
+
+fn read_json<T: DeserializeOwned>(archive: &mut zip::ZipArchive<File>, name: &str) -> anyhow::Result<T> {
+    let entry = archive.by_name(name).with_context(|| format!("The modpack has no {}", name))?;
+    serde_json::from_reader(entry).with_context(|| format!("Failed to parse {}", name))
+}
+
+fn sha512_file(path: &Path) -> anyhow::Result<String> {
+    let mut file = File::open(path).with_context(|| format!("Failed to open {:?} for hashing", path))?;
+    let mut hasher = Sha512::new();
+    std::io::copy(&mut file, &mut hasher).with_context(|| format!("Failed to hash {:?}", path))?;
+    Ok(format!("{:x}", hasher.finalize()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::install::tests::{serve, sha1_hex, stub_installer, vanilla_routes};
+    use tokio::net::TcpListener;
+
+    /// Writes a pack archive with the given entries.
+    fn pack(dir: &Path, name: &str, entries: &[(&str, &[u8])]) -> PathBuf {
+        let path = dir.join(name);
+        let mut zip = zip::ZipWriter::new(File::create(&path).unwrap());
+        for (name, content) in entries {
+            zip.start_file(*name, zip::write::FileOptions::default()).unwrap();
+            zip.write_all(content).unwrap();
+        }
+        zip.finish().unwrap();
+        path
+    }
+
+    #[tokio::test]
+    async fn install_curseforge_pack() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let base_url = format!("http://127.0.0.1:{}", listener.local_addr().unwrap().port());
+        let jei = b"jei".to_vec();
+        let mut routes = vanilla_routes();
+        routes.extend([
+            (
+                "/v1/mods/238222/files/4712866".to_string(),
+                serde_json::json!({"data": {
+                    "id": 4712866,
+                    "fileName": "jei-1.20.1-forge-15.2.0.27.jar",
+                    "downloadUrl": format!("{}/files/4712/866/jei-1.20.1-forge-15.2.0.27.jar", base_url),
+                    "hashes": [{"value": sha1_hex(&jei), "algo": 1}, {"value": "0", "algo": 2}],
+                    "fileLength": jei.len()
+                }})
+                .to_string()
+                .into_bytes(),
+            ),
+            ("/files/4712/866/jei-1.20.1-forge-15.2.0.27.jar".to_string(), jei),
+            // The author of this one opted out of third-party downloads
+            (
+                "/v1/mods/60089/files/4571394".to_string(),
+                br#"{"data": {"id": 4571394, "fileName": "Mekanism-1.20.1-10.4.0.14.jar", "downloadUrl": null, "hashes": []}}"#.to_vec(),
+            ),
+        ]);
+        serve(listener, routes);
+
+        let manifest = serde_json::json!({
+            "minecraft": {"version": "1.20.1", "modLoaders": []},
+            "manifestType": "minecraftModpack",
+            "manifestVersion": 1,
+            "name": "Stub Pack",
+            "version": "1.0",
+            "author": "someone",
+            "files": [
+                {"projectID": 238222, "fileID": 4712866, "required": true},
+                {"projectID": 60089, "fileID": 4571394, "required": true},
+                {"projectID": 1, "fileID": 2, "required": false}
+            ],
+            "overrides": "overrides"
+        });
+        let dir = tempfile::tempdir().unwrap();
+        let pack_path = pack(dir.path(), "pack.zip", &[
+            ("manifest.json", manifest.to_string().as_bytes()),
+            ("overrides/config/jei/jei-client.ini", b"[appearance]"),
+        ]);
+        let installer = stub_installer(&dir.path().join("minecraft"), &base_url);
+        let curseforge = CurseForgeApi { api_url: base_url.clone(), api_key: None };
+        let game_dir = dir.path().join("instance");
+
+        let record = install_modpack(&installer, &curseforge, &pack_path, &game_dir).await.unwrap();
+        assert_eq!(record.version_id, "1.20.1");
+        assert_eq!(fs::read(game_dir.join("mods/jei-1.20.1-forge-15.2.0.27.jar")).unwrap(), b"jei");
+        assert_eq!(fs::read_to_string(game_dir.join("config/jei/jei-client.ini")).unwrap(), "[appearance]");
+
+        // Listed for a manual download, not fetched from a guessed URL
+        let manual = record.files.iter().find(|file| file.path == "mods/Mekanism-1.20.1-10.4.0.14.jar").unwrap();
+        assert_eq!(manual.url, None);
+        assert!(!game_dir.join("mods/Mekanism-1.20.1-10.4.0.14.jar").exists());
+        assert!(load_record(&game_dir).unwrap().is_some());
+    }
+
+    fn sha512_hex(bytes: &[u8]) -> String {
+        format!("{:x}", Sha512::digest(bytes))
+    }
+
+    #[tokio::test]
+    async fn install_mrpack_pack() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let base_url = format!("http://127.0.0.1:{}", listener.local_addr().unwrap().port());
+        let sodium = b"sodium-fabric-0.5.3".to_vec();
+        let lithium = b"lithium-fabric-0.11.2".to_vec();
+        let mut routes = vanilla_routes();
+        routes.extend([
+            ("/data/AANobbMI/versions/OihdIimA/sodium-fabric-0.5.3.jar".to_string(), sodium.clone()),
+            ("/data/gvQqBUqZ/versions/ZSNsJrPI/lithium-fabric-0.11.2.jar".to_string(), lithium.clone()),
+        ]);
+        serve(listener, routes);
+
+        let index = serde_json::json!({
+            "formatVersion": 1,
+            "game": "minecraft",
+            "versionId": "1.0.0",
+            "name": "Stub Pack",
+            "files": [
+                {
+                    "path": "mods/sodium-fabric-0.5.3.jar",
+                    "hashes": {"sha1": sha1_hex(&sodium), "sha512": sha512_hex(&sodium)},
+                    "env": {"client": "required", "server": "unsupported"},
+                    "downloads": [format!("{}/data/AANobbMI/versions/OihdIimA/sodium-fabric-0.5.3.jar", base_url)],
+                    "fileSize": sodium.len()
+                },
+                // Only the size and SHA-512 to go on, so a stale copy of the same size gets past the download check
+                {
+                    "path": "mods/lithium-fabric-0.11.2.jar",
+                    "hashes": {"sha512": sha512_hex(&lithium)},
+                    "downloads": [format!("{}/data/gvQqBUqZ/versions/ZSNsJrPI/lithium-fabric-0.11.2.jar", base_url)],
+                    "fileSize": lithium.len()
+                },
+                // Not served by the stub, the download would fail
+                {
+                    "path": "mods/server-only.jar",
+                    "hashes": {"sha1": "0", "sha512": "0"},
+                    "env": {"client": "unsupported", "server": "required"},
+                    "downloads": [format!("{}/missing.jar", base_url)],
+                    "fileSize": 1
+                }
+            ],
+            "dependencies": {"minecraft": "1.20.1"}
+        });
+        let dir = tempfile::tempdir().unwrap();
+        let pack_path = pack(dir.path(), "pack.mrpack", &[
+            (MRPACK_INDEX_FILE, index.to_string().as_bytes()),
+            ("overrides/options.txt", b"fov:0.0"),
+            ("overrides/config/sodium-options.json", b"common"),
+            ("client-overrides/config/sodium-options.json", b"client"),
+        ]);
+        let installer = stub_installer(&dir.path().join("minecraft"), &base_url);
+        let curseforge = CurseForgeApi { api_url: base_url.clone(), api_key: None };
+        let game_dir = dir.path().join("instance");
+        fs::create_dir_all(game_dir.join("mods")).unwrap();
+        fs::write(game_dir.join("mods/lithium-fabric-0.11.2.jar"), b"lithium-fabric-0.10.0").unwrap();
+
+        let record = install_modpack(&installer, &curseforge, &pack_path, &game_dir).await.unwrap();
+        assert_eq!(record.version_id, "1.20.1");
+        assert_eq!(record.files.len(), 2);
+        assert_eq!(fs::read(game_dir.join("mods/sodium-fabric-0.5.3.jar")).unwrap(), sodium);
+        assert_eq!(fs::read(game_dir.join("mods/lithium-fabric-0.11.2.jar")).unwrap(), lithium);
+        assert!(!game_dir.join("mods/server-only.jar").exists());
+        assert_eq!(fs::read_to_string(game_dir.join("options.txt")).unwrap(), "fov:0.0");
+        assert_eq!(fs::read_to_string(game_dir.join("config/sodium-options.json")).unwrap(), "client");
+    }
+
+    #[tokio::test]
+    async fn mrpack_paths_stay_inside_the_game_directory() {
+        let index = serde_json::json!({
+            "formatVersion": 1,
+            "game": "minecraft",
+            "versionId": "1.0.0",
+            "name": "Evil Pack",
+            "files": [{"path": "../evil.jar", "hashes": {}, "downloads": ["https://cdn.modrinth.com/evil.jar"], "fileSize": 1}],
+            "dependencies": {"minecraft": "1.20.1"}
+        });
+        let dir = tempfile::tempdir().unwrap();
+        let pack_path = pack(dir.path(), "evil.mrpack", &[(MRPACK_INDEX_FILE, index.to_string().as_bytes())]);
+        let installer = stub_installer(&dir.path().join("minecraft"), "http://127.0.0.1:9");
+        let curseforge = CurseForgeApi { api_url: "http://127.0.0.1:9".to_string(), api_key: None };
+        let error = install_modpack(&installer, &curseforge, &pack_path, &dir.path().join("instance")).await.unwrap_err();
+        assert!(error.to_string().contains("outside the game directory"), "{:#}", error);
+    }
+
+    #[test]
+    fn safe_relative_paths() {
+        assert_eq!(safe_relative_path("mods/a.jar").unwrap(), PathBuf::from("mods/a.jar"));
+        for path in ["", "../a.jar", "mods/../../a.jar", "/etc/passwd", "./mods/a.jar"] {
+            assert!(safe_relative_path(path).is_err(), "{} was accepted", path);
+        }
+    }
+}
@@ -0,0 +1,375 @@
+use crate::models::{Artifact, Library, Rule};
+use regex::Regex;
+
+/// Launcher features that `features` rules can ask for.
+#[derive(Debug, Clone, Default)]
+pub struct Features {
+    pub is_demo_user: bool,
+    pub has_custom_resolution: bool,
+    pub has_quick_plays_support: bool,
+    pub is_quick_play_singleplayer: bool,
+    pub is_quick_play_multiplayer: bool,
+    pub is_quick_play_realms: bool,
+}
+
+impl Features {
+    fn is_enabled(&self, name: &str) -> bool {
+        match name {
+            "is_demo_user" => self.is_demo_user,
+            "has_custom_resolution" => self.has_custom_resolution,
+            "has_quick_plays_support" => self.has_quick_plays_support,
+            "is_quick_play_singleplayer" => self.is_quick_play_singleplayer,
+            "is_quick_play_multiplayer" => self.is_quick_play_multiplayer,
+            "is_quick_play_realms" => self.is_quick_play_realms,
+            _ => false,
+        }
+    }
+}
+
+/// The platform and feature set that library and argument rules are evaluated against.
+#[derive(Debug, Clone)]
+pub struct RuleContext {
+    /// Mojang OS name: `windows`, `linux` or `osx`
+    pub os_name: String,
+    /// Mojang-style architecture, e.g. `x86`, `x86_64`, `arm64`
+    pub os_arch: String,
+    pub os_version: String,
+    pub features: Features,
+}
+
+impl RuleContext {
+    pub fn current(features: Features) -> Self {
+        let os_name = match std::env::consts::OS {
+            "macos" => "osx",
+            other => other,
+        };
+        Self {
+            os_name: os_name.to_string(),
+            os_arch: normalize_arch(std::env::consts::ARCH).to_string(),
+            os_version: current_os_version(),
+            features,
+        }
+    }
+
+    /// Mojang's semantics: nothing is allowed unless a rule matches, and the last matching rule wins.
+    pub fn allows(&self, rules: &[Rule]) -> bool {
+        let mut allowed = false;
+        for rule in rules {
+            if self.matches(rule) {
+                allowed = rule.action == "allow";
+            }
+        }
+        allowed
+    }
+
+    pub fn library_allowed(&self, library: &Library) -> bool {
+        library.rules.as_deref().is_none_or(|rules| self.allows(rules))
+    }
+
+    fn matches(&self, rule: &Rule) -> bool {
+        if let Some(os) = &rule.os {
+            if let Some(name) = &os.name
+                && *name != self.os_name {
+                return false;
+            }
+            if let Some(arch) = &os.arch
+                && normalize_arch(arch) != self.os_arch {
+                return false;
+            }
+            if let Some(version) = &os.version {
+                match Regex::new(version) {
+                    Ok(re) if re.is_match(&self.os_version) => {}
+                    _ => return false,
+                }
+            }
+        }
+
+        if let Some(features) = &rule.features
+            && features.iter().any(|(name, expected)| self.features.is_enabled(name) != *expected) {
+            return false;
+        }
+
+        true
+    }
+
+    /// Picks the natives classifier of a library for this platform, if it has one.
+    /// Classifiers without an architecture hold x86 natives, so on arm64 only one naming arm64 is used.
+    pub fn native_artifact<'a>(&self, library: &'a Library) -> Option<&'a Artifact> {
+        let classifiers = library.downloads.as_ref()?.classifiers.as_ref()?;
+        let arm64 = self.os_arch == "arm64";
+
+        // Legacy libraries name their classifier per OS, e.g. "natives-windows-${arch}"; none were built for ARM
+        if let Some(natives) = &library.natives {
+            if arm64 {
+                return None;
+            }
+            let classifier = natives.get(&self.os_name)?;
+            let bits = if self.os_arch.ends_with("64") { "64" } else { "32" };
+            return classifiers.get(&classifier.replace("${arch}", bits));
+        }
+
+        let os_key = self.natives_os_key();
+        // LWJGL names 64-bit ARM "arm64" on macOS and Windows but "aarch64" on Linux
+        let arch_keys: &[&str] = match self.os_arch.as_str() {
+            "x86_64" => &["x64"],
+            "arm64" => &["arm64", "aarch64"],
+            other => &[other],
+        };
+        let for_arch = arch_keys
+            .iter()
+            .find_map(|arch_key| classifiers.get(&format!("natives-{}-{}", os_key, arch_key)));
+        if arm64 {
+            return for_arch;
+        }
+        for_arch.or_else(|| classifiers.get(&format!("natives-{}", os_key)))
+    }
+
+    /// Whether a library has natives for this OS, but none [`native_artifact`](Self::native_artifact)
+    /// can use on this architecture.
+    pub fn lacks_arch_natives(&self, library: &Library) -> bool {
+        let has_os_natives = match &library.natives {
+            Some(natives) => natives.contains_key(&self.os_name),
+            None => library
+                .downloads
+                .as_ref()
+                .and_then(|downloads| downloads.classifiers.as_ref())
+                .is_some_and(|classifiers| classifiers.get(&format!("natives-{}", self.natives_os_key())).is_some()),
+        };
+        has_os_natives && self.native_artifact(library).is_none()
+    }
+
+    /// The OS as natives classifiers spell it.
+    fn natives_os_key(&self) -> &str {
+        match self.os_name.as_str() {
+            "osx" => "macos",
+            other => other,
+        }
+    }
+}
+
+/// One spelling per architecture, whether it comes from Rust, a rule or a Java installation.
+pub(crate) fn normalize_arch(arch: &str) -> &str {
+    match arch {
+        "amd64" | "x86_64" | "x64" => "x86_64",
+        "i386" | "i586" | "i686" | "x86" => "x86",
+        "aarch64" | "arm64" => "arm64",
+        other => other,
+    }
+}
+
+/// Same value Java reports as `os.version`, which is what the `os.version` regexes are written against.
+fn current_os_version() -> String {
+    #[cfg(target_os = "linux")]
+    {
+        std::fs::read_to_string("/proc/sys/kernel/osrelease")
+            .map(|v| v.trim().to_string())
+            .unwrap_or_default()
+    }
+    #[cfg(target_os = "macos")]
+    {
+        std::process::Command::new("sw_vers")
+            .arg("-productVersion")
+            .output()
+            .map(|o| String::from_utf8_lossy(&o.stdout).trim().to_string())
+            .unwrap_or_default()
+    }
+    #[cfg(target_os = "windows")]
+    {
+        // "Microsoft Windows [Version 10.0.19045.2965]" -> "10.0"
+        std::process::Command::new("cmd")
+            .args(["/C", "ver"])
+            .output()
+            .ok()
+            .and_then(|o| {
+                let text = String::from_utf8_lossy(&o.stdout).to_string();
+                let version = text.split("Version ").nth(1)?.trim_end_matches(|c: char| !c.is_ascii_digit()).to_string();
+                Some(version.splitn(3, '.').take(2).collect::<Vec<_>>().join("."))
+            })
+            .unwrap_or_default()
+    }
+    #[cfg(not(any(target_os = "linux", target_os = "macos", target_os = "windows")))]
+    {
+        String::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn context(os_name: &str, os_arch: &str, os_version: &str, features: Features) -> RuleContext {
+        RuleContext {
+            os_name: os_name.to_string(),
+            os_arch: normalize_arch(os_arch).to_string(),
+            os_version: os_version.to_string(),
+            features,
+        }
+    }
+
+    fn rules(json: &str) -> Vec<Rule> {
+        serde_json::from_str(json).expect("valid rules fixture")
+    }
+
+    fn library(json: &str) -> Library {
+        serde_json::from_str(json).expect("valid library fixture")
+    }
+
+    // 1.12.2: every platform except macOS
+    const ALLOW_THEN_DISALLOW_OSX: &str = r#"[{"action": "allow"}, {"action": "disallow", "os": {"name": "osx"}}]"#;
+    // 1.12.2: macOS only
+    const ALLOW_OSX: &str = r#"[{"action": "allow", "os": {"name": "osx"}}]"#;
+    // 1.19 arguments.jvm
+    const WINDOWS_10: &str = r#"[{"action": "allow", "os": {"name": "windows", "version": "^10\\."}}]"#;
+    const X86: &str = r#"[{"action": "allow", "os": {"arch": "x86"}}]"#;
+    // 1.21 arguments.game
+    const DEMO: &str = r#"[{"action": "allow", "features": {"is_demo_user": true}}]"#;
+    const RESOLUTION: &str = r#"[{"action": "allow", "features": {"has_custom_resolution": true}}]"#;
+    const QUICK_PLAY_SUPPORT: &str = r#"[{"action": "allow", "features": {"has_quick_plays_support": true}}]"#;
+    const QUICK_PLAY_SINGLEPLAYER: &str = r#"[{"action": "allow", "features": {"is_quick_play_singleplayer": true}}]"#;
+    const QUICK_PLAY_MULTIPLAYER: &str = r#"[{"action": "allow", "features": {"is_quick_play_multiplayer": true}}]"#;
+    const QUICK_PLAY_REALMS: &str = r#"[{"action": "allow", "features": {"is_quick_play_realms": true}}]"#;
+
+    #[test]
+    fn allows() {
+        let linux = context("linux", "amd64", "6.8.0", Features::default());
+        let osx = context("osx", "aarch64", "14.5", Features::default());
+        let windows_10 = context("windows", "amd64", "10.0", Features::default());
+        let windows_11_x86 = context("windows", "x86", "11.0", Features::default());
+
+        let cases: &[(&str, &str, &RuleContext, bool)] = &[
+            ("no rules", "[]", &linux, false),
+            ("allow then disallow osx on linux", ALLOW_THEN_DISALLOW_OSX, &linux, true),
+            ("allow then disallow osx on osx", ALLOW_THEN_DISALLOW_OSX, &osx, false),
+            ("osx only on osx", ALLOW_OSX, &osx, true),
+            ("osx only on linux", ALLOW_OSX, &linux, false),
+            ("last match wins", r#"[{"action": "disallow"}, {"action": "allow"}]"#, &linux, true),
+            (
+                "disallow after allow",
+                r#"[{"action": "allow", "os": {"name": "linux"}}, {"action": "disallow", "os": {"name": "linux"}}]"#,
+                &linux,
+                false,
+            ),
+            ("os.version regex matches", WINDOWS_10, &windows_10, true),
+            ("os.version regex does not match", WINDOWS_10, &windows_11_x86, false),
+            ("x86 on x86", X86, &windows_11_x86, true),
+            ("x86 on x86_64", X86, &windows_10, false),
+            ("x86 on arm64", X86, &osx, false),
+        ];
+        for (name, json, context, expected) in cases {
+            assert_eq!(context.allows(&rules(json)), *expected, "{}", name);
+        }
+    }
+
+    #[test]
+    fn allows_features() {
+        let all = Features {
+            is_demo_user: true,
+            has_custom_resolution: true,
+            has_quick_plays_support: true,
+            is_quick_play_singleplayer: true,
+            is_quick_play_multiplayer: true,
+            is_quick_play_realms: true,
+        };
+        let cases: &[(&str, Features)] = &[
+            (DEMO, Features { is_demo_user: true, ..Default::default() }),
+            (RESOLUTION, Features { has_custom_resolution: true, ..Default::default() }),
+            (QUICK_PLAY_SUPPORT, Features { has_quick_plays_support: true, ..Default::default() }),
+            (QUICK_PLAY_SINGLEPLAYER, Features { is_quick_play_singleplayer: true, ..Default::default() }),
+            (QUICK_PLAY_MULTIPLAYER, Features { is_quick_play_multiplayer: true, ..Default::default() }),
+            (QUICK_PLAY_REALMS, Features { is_quick_play_realms: true, ..Default::default() }),
+        ];
+        for (json, enabled) in cases {
+            let rules = rules(json);
+            assert!(context("linux", "x86_64", "", enabled.clone()).allows(&rules), "{} enabled", json);
+            assert!(context("linux", "x86_64", "", all.clone()).allows(&rules), "{} with all features", json);
+            assert!(!context("linux", "x86_64", "", Features::default()).allows(&rules), "{} disabled", json);
+        }
+        // A feature asked to be off matches only while it is off
+        let not_demo = rules(r#"[{"action": "allow", "features": {"is_demo_user": false}}]"#);
+        assert!(context("linux", "x86_64", "", Features::default()).allows(&not_demo));
+        assert!(!context("linux", "x86_64", "", all).allows(&not_demo));
+    }
+
+    // 1.12.2: LWJGL 2 natives with the legacy `natives` map
+    const LWJGL2_NATIVES: &str = r#"{
+        "downloads": {"classifiers": {
+            "natives-linux": {"path": "org/lwjgl/lwjgl/lwjgl-platform/2.9.4-nightly-20150209/lwjgl-platform-2.9.4-nightly-20150209-natives-linux.jar", "sha1": "931074f46c795d2f7b30ed6395df5715cfd7675b", "size": 578680, "url": "https://libraries.minecraft.net/org/lwjgl/lwjgl/lwjgl-platform/2.9.4-nightly-20150209/lwjgl-platform-2.9.4-nightly-20150209-natives-linux.jar"},
+            "natives-osx": {"path": "org/lwjgl/lwjgl/lwjgl-platform/2.9.4-nightly-20150209/lwjgl-platform-2.9.4-nightly-20150209-natives-osx.jar", "sha1": "bcab850f8f487c3f4c4dbabde778bb82bd1a40ed", "size": 426822, "url": "https://libraries.minecraft.net/org/lwjgl/lwjgl/lwjgl-platform/2.9.4-nightly-20150209/lwjgl-platform-2.9.4-nightly-20150209-natives-osx.jar"},
+            "natives-windows": {"path": "org/lwjgl/lwjgl/lwjgl-platform/2.9.4-nightly-20150209/lwjgl-platform-2.9.4-nightly-20150209-natives-windows.jar", "sha1": "b84d5102b9dbfabfeb5e43c7e2828d98a7fc80e0", "size": 613748, "url": "https://libraries.minecraft.net/org/lwjgl/lwjgl/lwjgl-platform/2.9.4-nightly-20150209/lwjgl-platform-2.9.4-nightly-20150209-natives-windows.jar"}
+        }},
+        "extract": {"exclude": ["META-INF/"]},
+        "name": "org.lwjgl.lwjgl:lwjgl-platform:2.9.4-nightly-20150209",
+        "natives": {"linux": "natives-linux", "osx": "natives-osx", "windows": "natives-windows"}
+    }"#;
+    // 1.8.9: Twitch natives picked by `${arch}`
+    const TWITCH_NATIVES: &str = r#"{
+        "downloads": {"classifiers": {
+            "natives-windows-32": {"path": "tv/twitch/twitch-external-platform/4.5/twitch-external-platform-4.5-natives-windows-32.jar", "sha1": "18215140f010c05b9f86ef6f0f8871954d2ccebf", "size": 5654047, "url": "https://libraries.minecraft.net/tv/twitch/twitch-external-platform/4.5/twitch-external-platform-4.5-natives-windows-32.jar"},
+            "natives-windows-64": {"path": "tv/twitch/twitch-external-platform/4.5/twitch-external-platform-4.5-natives-windows-64.jar", "sha1": "c3cde57891b935d41b6680a9c5e1502eeab76d86", "size": 7457619, "url": "https://libraries.minecraft.net/tv/twitch/twitch-external-platform/4.5/twitch-external-platform-4.5-natives-windows-64.jar"}
+        }},
+        "extract": {"exclude": ["META-INF/"]},
+        "name": "tv.twitch:twitch-external-platform:4.5",
+        "natives": {"windows": "natives-windows-${arch}"},
+        "rules": [{"action": "allow", "os": {"name": "windows"}}]
+    }"#;
+    // 1.16.5: LWJGL 3 natives named by classifier, x86 builds only
+    const LWJGL3_NATIVES: &str = r#"{
+        "downloads": {
+            "artifact": {"path": "org/lwjgl/lwjgl/3.2.2/lwjgl-3.2.2.jar", "sha1": "8ad6294407e15780b43e84929c40e4c5e997972e", "size": 321900, "url": "https://libraries.minecraft.net/org/lwjgl/lwjgl/3.2.2/lwjgl-3.2.2.jar"},
+            "classifiers": {
+                "natives-linux": {"path": "org/lwjgl/lwjgl/3.2.2/lwjgl-3.2.2-natives-linux.jar", "sha1": "ae7976827ca2a3741f6b9a843a89bacd637af350", "size": 124776, "url": "https://libraries.minecraft.net/org/lwjgl/lwjgl/3.2.2/lwjgl-3.2.2-natives-linux.jar"},
+                "natives-macos": {"path": "org/lwjgl/lwjgl/3.2.2/lwjgl-3.2.2-natives-macos.jar", "sha1": "bbfb75693bdb714c0c69c2c9f9be73d259b43b62", "size": 48462, "url": "https://libraries.minecraft.net/org/lwjgl/lwjgl/3.2.2/lwjgl-3.2.2-natives-macos.jar"},
+                "natives-windows": {"path": "org/lwjgl/lwjgl/3.2.2/lwjgl-3.2.2-natives-windows.jar", "sha1": "05359f3aa50d36352815fc662ea73e1c00d22170", "size": 279593, "url": "https://libraries.minecraft.net/org/lwjgl/lwjgl/3.2.2/lwjgl-3.2.2-natives-windows.jar"}
+            }
+        },
+        "name": "org.lwjgl:lwjgl:3.2.2"
+    }"#;
+    const NO_NATIVES: &str = r#"{
+        "downloads": {"artifact": {"path": "com/mojang/brigadier/1.0.17/brigadier-1.0.17.jar", "sha1": "c6b7dc51dd44379cc751b7504816006e9be4b1e6", "size": 77392, "url": "https://libraries.minecraft.net/com/mojang/brigadier/1.0.17/brigadier-1.0.17.jar"}},
+        "name": "com.mojang:brigadier:1.0.17"
+    }"#;
+
+    #[test]
+    fn native_artifact() {
+        let cases: &[(&str, &str, &str, Option<&str>)] = &[
+            (LWJGL2_NATIVES, "linux", "x86_64", Some("lwjgl-platform-2.9.4-nightly-20150209-natives-linux.jar")),
+            (LWJGL2_NATIVES, "osx", "x86_64", Some("lwjgl-platform-2.9.4-nightly-20150209-natives-osx.jar")),
+            (LWJGL2_NATIVES, "windows", "x86", Some("lwjgl-platform-2.9.4-nightly-20150209-natives-windows.jar")),
+            (TWITCH_NATIVES, "windows", "x86_64", Some("twitch-external-platform-4.5-natives-windows-64.jar")),
+            (TWITCH_NATIVES, "windows", "x86", Some("twitch-external-platform-4.5-natives-windows-32.jar")),
+            (TWITCH_NATIVES, "linux", "x86_64", None),
+            (LWJGL3_NATIVES, "linux", "x86_64", Some("lwjgl-3.2.2-natives-linux.jar")),
+            (LWJGL3_NATIVES, "osx", "x86_64", Some("lwjgl-3.2.2-natives-macos.jar")),
+            (LWJGL3_NATIVES, "windows", "x86_64", Some("lwjgl-3.2.2-natives-windows.jar")),
+            (LWJGL3_NATIVES, "windows", "x86", Some("lwjgl-3.2.2-natives-windows.jar")),
+            // The x86_64 natives would only fail to load on ARM
+            (LWJGL2_NATIVES, "osx", "aarch64", None),
+            (LWJGL3_NATIVES, "linux", "aarch64", None),
+            (LWJGL3_NATIVES, "osx", "arm64", None),
+            (NO_NATIVES, "linux", "x86_64", None),
+        ];
+        for (json, os_name, os_arch, expected) in cases {
+            let library = library(json);
+            let context = context(os_name, os_arch, "", Features::default());
+            let artifact = context.native_artifact(&library).and_then(|artifact| artifact.path.rsplit('/').next());
+            assert_eq!(artifact, *expected, "{} on {} {}", library.name, os_name, os_arch);
+        }
+    }
+
+    #[test]
+    fn lacks_arch_natives() {
+        let cases: &[(&str, &str, &str, bool)] = &[
+            (LWJGL3_NATIVES, "linux", "aarch64", true),
+            (LWJGL2_NATIVES, "osx", "arm64", true),
+            (LWJGL3_NATIVES, "linux", "x86_64", false),
+            // No natives for the OS at all is not an architecture problem
+            (TWITCH_NATIVES, "linux", "aarch64", false),
+            (NO_NATIVES, "linux", "aarch64", false),
+        ];
+        for (json, os_name, os_arch, expected) in cases {
+            let library = library(json);
+            let context = context(os_name, os_arch, "", Features::default());
+            assert_eq!(context.lacks_arch_natives(&library), *expected, "{} on {} {}", library.name, os_name, os_arch);
+        }
+    }
+}
@@ -0,0 +1,319 @@
+use crate::error::LauncherError;
+use crate::models::VersionDetails;
+use anyhow::Context;
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Reads `versions/<id>/<id>.json` and resolves its `inheritsFrom` chain.
+pub fn load_version(versions_dir: &Path, version_id: &str) -> anyhow::Result<VersionDetails> {
+    let details = read_version_json(versions_dir, version_id)?;
+    resolve_version(versions_dir, details)
+}
+
+/// Folds every `inheritsFrom` parent (read from `versions_dir`) into `details`,
+/// so modded profiles come out as one complete version.
+pub fn resolve_version(versions_dir: &Path, details: VersionDetails) -> anyhow::Result<VersionDetails> {
+    let mut seen = vec![details.id.clone()];
+    let mut chain = Vec::new();
+    let mut next = details.inherits_from.clone();
+    chain.push(details);
+
+    while let Some(parent_id) = next {
+        if seen.contains(&parent_id) {
+            seen.push(parent_id);
+            return Err(LauncherError::InheritanceCycle(seen).into());
+        }
+        let parent = read_version_json(versions_dir, &parent_id)
+            .with_context(|| format!("Version {} inherits from {}", seen.last().unwrap(), parent_id))?;
+        next = parent.inherits_from.clone();
+        seen.push(parent_id);
+        chain.push(parent);
+    }
+
+    let mut resolved = chain.pop().expect("chain starts with the requested version");
+    while let Some(child) = chain.pop() {
+        resolved = merge(resolved, child);
+    }
+    Ok(resolved)
+}
+
+/// The client JAR a version is launched with, `versions/<id>/<id>.jar` as with the official launcher.
+/// A version running on another version's JAR gets a copy of it there, so the JAR is named after
+/// `${version_name}` (Forge leaves it out of its module path by that name).
+pub fn client_jar(versions_dir: &Path, details: &VersionDetails) -> anyhow::Result<PathBuf> {
+    let jar_path = |id: &str| versions_dir.join(id).join(format!("{}.jar", id));
+    let client_jar = jar_path(&details.id);
+    let source = jar_path(details.jar_id());
+    if details.jar_id() != details.id
+        && let Ok(source_metadata) = fs::metadata(&source)
+        && fs::metadata(&client_jar).map_or(true, |metadata| metadata.len() != source_metadata.len()) {
+        fs::create_dir_all(versions_dir.join(&details.id))?;
+        fs::copy(&source, &client_jar)
+            .with_context(|| format!("Failed to copy {:?} to {:?}", source, client_jar))?;
+    }
+    Ok(client_jar)
+}
+
+fn read_version_json(versions_dir: &Path, version_id: &str) -> anyhow::Result<VersionDetails> {
+    let path = versions_dir.join(version_id).join(format!("{}.json", version_id));
+    if !path.exists() {
+        return Err(LauncherError::VersionNotFound(version_id.to_string()).into());
+    }
+    let json = fs::read_to_string(&path).with_context(|| format!("Failed to read {:?}", path))?;
+    serde_json::from_str(&json).with_context(|| format!("Failed to parse {:?}", path))
+}
+
+/// Child values win; libraries are combined with the child's replacing the parent's
+/// for the same artifact, and arguments are appended to the parent's.
+fn merge(parent: VersionDetails, child: VersionDetails) -> VersionDetails {
+    // Without downloads of its own, the child runs on the parent's client JAR
+    let jar = child
+        .jar
+        .clone()
+        .or_else(|| child.downloads.is_none().then(|| parent.jar_id().to_string()));
+
+    let overridden: HashSet<String> = child.libraries.iter().map(|l| library_key(&l.name)).collect();
+    let mut libraries = child.libraries;
+    libraries.extend(
+        parent
+            .libraries
+            .into_iter()
+            .filter(|l| !overridden.contains(&library_key(&l.name))),
+    );
+
+    let arguments = match (parent.arguments, child.arguments) {
+        (Some(mut parent_args), Some(child_args)) => {
+            parent_args.game.extend(child_args.game);
+            parent_args.jvm.extend(child_args.jvm);
+            Some(parent_args)
+        }
+        (parent_args, child_args) => child_args.or(parent_args),
+    };
+
+    VersionDetails {
+        id: child.id,
+        version_type: child.version_type,
+        inherits_from: None,
+        jar,
+        downloads: child.downloads.or(parent.downloads),
+        libraries,
+        main_class: if child.main_class.is_empty() { parent.main_class } else { child.main_class },
+        minecraft_arguments: child.minecraft_arguments.or(parent.minecraft_arguments),
+        arguments,
+        asset_index: child.asset_index.or(parent.asset_index),
+        java_version: child.java_version.or(parent.java_version),
+        logging: child.logging.or(parent.logging),
+        release_time: child.release_time.or(parent.release_time),
+    }
+}
+
+/// `group:artifact[:classifier]` of a Maven coordinate, ignoring the version.
+fn library_key(name: &str) -> String {
+    let name = name.split('@').next().unwrap_or(name);
+    match name.split(':').collect::<Vec<_>>().as_slice() {
+        [group, artifact, _version, classifier, ..] => format!("{}:{}:{}", group, artifact, classifier),
+        [group, artifact, ..] => format!("{}:{}", group, artifact),
+        _ => name.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::Argument;
+
+    // Trimmed 1.20.1 client JSON
+    const VANILLA: &str = r#"{
+        "id": "1.20.1",
+        "type": "release",
+        "mainClass": "net.minecraft.client.main.Main",
+        "downloads": {"client": {"sha1": "0c3ec587af28e5a785c0b4a7b8a30f9a8f78f838", "size": 23028853, "url": "https://piston-data.mojang.com/v1/objects/0c3ec587af28e5a785c0b4a7b8a30f9a8f78f838/client.jar"}},
+        "assetIndex": {"id": "5", "sha1": "f6ab7d4b9a5a1e0b4e5a6a9c1a8e5d2c1b0a9f8e", "size": 414581, "totalSize": 622697934, "url": "https://piston-meta.mojang.com/v1/packages/f6ab7d4b9a5a1e0b4e5a6a9c1a8e5d2c1b0a9f8e/5.json"},
+        "javaVersion": {"component": "java-runtime-gamma", "majorVersion": 17},
+        "arguments": {
+            "game": ["--username", "${auth_player_name}", "--version", "${version_name}"],
+            "jvm": ["-Djava.library.path=${natives_directory}", "-cp", "${classpath}"]
+        },
+        "libraries": [
+            {"name": "org.ow2.asm:asm:9.3", "downloads": {"artifact": {"path": "org/ow2/asm/asm/9.3/asm-9.3.jar", "sha1": "1", "size": 1, "url": "https://libraries.minecraft.net/org/ow2/asm/asm/9.3/asm-9.3.jar"}}},
+            {"name": "com.mojang:brigadier:1.1.8", "downloads": {"artifact": {"path": "com/mojang/brigadier/1.1.8/brigadier-1.1.8.jar", "sha1": "2", "size": 1, "url": "https://libraries.minecraft.net/com/mojang/brigadier/1.1.8/brigadier-1.1.8.jar"}}},
+            {"name": "org.lwjgl:lwjgl:3.3.1", "downloads": {"artifact": {"path": "org/lwjgl/lwjgl/3.3.1/lwjgl-3.3.1.jar", "sha1": "3", "size": 1, "url": "https://libraries.minecraft.net/org/lwjgl/lwjgl/3.3.1/lwjgl-3.3.1.jar"}}},
+            {"name": "org.lwjgl:lwjgl:3.3.1:natives-linux", "downloads": {"artifact": {"path": "org/lwjgl/lwjgl/3.3.1/lwjgl-3.3.1-natives-linux.jar", "sha1": "4", "size": 1, "url": "https://libraries.minecraft.net/org/lwjgl/lwjgl/3.3.1/lwjgl-3.3.1-natives-linux.jar"}}}
+        ]
+    }"#;
+
+    // Trimmed Fabric loader profile
+    const FABRIC: &str = r#"{
+        "id": "fabric-loader-0.15.11-1.20.1",
+        "inheritsFrom": "1.20.1",
+        "type": "release",
+        "mainClass": "net.fabricmc.loader.impl.launch.knot.KnotClient",
+        "arguments": {"game": [], "jvm": ["-DFabricMcEmu= net.minecraft.client.main.Main "]},
+        "libraries": [
+            {"name": "org.ow2.asm:asm:9.6", "url": "https://maven.fabricmc.net/"},
+            {"name": "net.fabricmc:fabric-loader:0.15.11", "url": "https://maven.fabricmc.net/"}
+        ]
+    }"#;
+
+    // Trimmed Forge 1.20.1 version JSON
+    const FORGE: &str = r#"{
+        "id": "1.20.1-forge-47.2.0",
+        "inheritsFrom": "1.20.1",
+        "type": "release",
+        "mainClass": "cpw.mods.bootstraplauncher.BootstrapLauncher",
+        "arguments": {
+            "game": ["--launchTarget", "forgeclient"],
+            "jvm": ["-DignoreList=bootstraplauncher,securejarhandler,${version_name}.jar"]
+        },
+        "libraries": [
+            {"name": "net.minecraftforge:forge:1.20.1-47.2.0:universal", "downloads": {"artifact": {"path": "net/minecraftforge/forge/1.20.1-47.2.0/forge-1.20.1-47.2.0-universal.jar", "sha1": "5", "size": 1, "url": "https://maven.minecraftforge.net/net/minecraftforge/forge/1.20.1-47.2.0/forge-1.20.1-47.2.0-universal.jar"}}},
+            {"name": "org.lwjgl:lwjgl:3.3.2", "downloads": {"artifact": {"path": "org/lwjgl/lwjgl/3.3.2/lwjgl-3.3.2.jar", "sha1": "6", "size": 1, "url": "https://libraries.minecraft.net/org/lwjgl/lwjgl/3.3.2/lwjgl-3.3.2.jar"}}}
+        ]
+    }"#;
+
+    fn versions_dir(profiles: &[(&str, &str)]) -> tempfile::TempDir {
+        let dir = tempfile::tempdir().expect("temp dir");
+        for (id, json) in profiles {
+            let version_dir = dir.path().join(id);
+            fs::create_dir_all(&version_dir).expect("version dir");
+            fs::write(version_dir.join(format!("{}.json", id)), json).expect("version JSON");
+        }
+        dir
+    }
+
+    fn library_names(details: &VersionDetails) -> Vec<&str> {
+        details.libraries.iter().map(|library| library.name.as_str()).collect()
+    }
+
+    fn plain_arguments(arguments: &[Argument]) -> Vec<&str> {
+        arguments
+            .iter()
+            .filter_map(|argument| match argument {
+                Argument::Plain(value) => Some(value.as_str()),
+                Argument::Conditional { .. } => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn library_keys() {
+        let cases = [
+            ("org.ow2.asm:asm:9.6", "org.ow2.asm:asm"),
+            ("org.lwjgl:lwjgl:3.3.1:natives-linux", "org.lwjgl:lwjgl:natives-linux"),
+            ("net.minecraftforge:forge:1.20.1-47.2.0:universal", "net.minecraftforge:forge:universal"),
+            ("de.oceanlabs.mcp:mcp_config:1.20.1-20230612.114412@zip", "de.oceanlabs.mcp:mcp_config"),
+        ];
+        for (name, expected) in cases {
+            assert_eq!(library_key(name), expected, "{}", name);
+        }
+    }
+
+    #[test]
+    fn fabric_profile() {
+        let dir = versions_dir(&[("1.20.1", VANILLA), ("fabric-loader-0.15.11-1.20.1", FABRIC)]);
+        let details = load_version(dir.path(), "fabric-loader-0.15.11-1.20.1").expect("resolves");
+
+        assert_eq!(details.id, "fabric-loader-0.15.11-1.20.1");
+        assert_eq!(details.inherits_from, None);
+        // The child's asm replaces the parent's, and the child's libraries come first
+        assert_eq!(
+            library_names(&details),
+            [
+                "org.ow2.asm:asm:9.6",
+                "net.fabricmc:fabric-loader:0.15.11",
+                "com.mojang:brigadier:1.1.8",
+                "org.lwjgl:lwjgl:3.3.1",
+                "org.lwjgl:lwjgl:3.3.1:natives-linux",
+            ]
+        );
+        let arguments = details.arguments.as_ref().expect("arguments");
+        assert_eq!(plain_arguments(&arguments.game), ["--username", "${auth_player_name}", "--version", "${version_name}"]);
+        assert_eq!(
+            plain_arguments(&arguments.jvm),
+            ["-Djava.library.path=${natives_directory}", "-cp", "${classpath}", "-DFabricMcEmu= net.minecraft.client.main.Main "]
+        );
+        // No downloads of its own, so it runs on the parent's client JAR
+        assert_eq!(details.jar.as_deref(), Some("1.20.1"));
+        assert_eq!(details.jar_id(), "1.20.1");
+        assert!(details.downloads.is_some());
+        assert_eq!(details.main_class, "net.fabricmc.loader.impl.launch.knot.KnotClient");
+        assert_eq!(details.asset_index.as_ref().map(|index| index.id.as_str()), Some("5"));
+        assert_eq!(details.java_version.as_ref().map(|java| java.major_version), Some(17));
+    }
+
+    #[test]
+    fn forge_profile() {
+        let forge_with_own_fields = FORGE.replacen(
+            r#""type": "release","#,
+            r#""type": "release",
+            "assetIndex": {"id": "forge", "sha1": "0", "size": 1, "url": "https://example.invalid/forge.json"},
+            "javaVersion": {"component": "java-runtime-delta", "majorVersion": 21},"#,
+            1,
+        );
+        let dir = versions_dir(&[("1.20.1", VANILLA), ("1.20.1-forge-47.2.0", &forge_with_own_fields)]);
+        let details = load_version(dir.path(), "1.20.1-forge-47.2.0").expect("resolves");
+
+        // A classifier is part of the key: the natives stay, the plain lwjgl is replaced
+        assert_eq!(
+            library_names(&details),
+            [
+                "net.minecraftforge:forge:1.20.1-47.2.0:universal",
+                "org.lwjgl:lwjgl:3.3.2",
+                "org.ow2.asm:asm:9.3",
+                "com.mojang:brigadier:1.1.8",
+                "org.lwjgl:lwjgl:3.3.1:natives-linux",
+            ]
+        );
+        let arguments = details.arguments.as_ref().expect("arguments");
+        assert_eq!(
+            plain_arguments(&arguments.game),
+            ["--username", "${auth_player_name}", "--version", "${version_name}", "--launchTarget", "forgeclient"]
+        );
+        assert_eq!(plain_arguments(&arguments.jvm).last(), Some(&"-DignoreList=bootstraplauncher,securejarhandler,${version_name}.jar"));
+        assert_eq!(details.jar_id(), "1.20.1");
+        assert_eq!(details.main_class, "cpw.mods.bootstraplauncher.BootstrapLauncher");
+        assert_eq!(details.asset_index.as_ref().map(|index| index.id.as_str()), Some("forge"));
+        assert_eq!(details.java_version.as_ref().map(|java| java.major_version), Some(21));
+    }
+
+    #[test]
+    fn child_with_downloads_keeps_its_own_jar() {
+        let own_client = FABRIC.replacen(
+            r#""type": "release","#,
+            r#""type": "release", "downloads": {"client": {"sha1": "7", "size": 1, "url": "https://example.invalid/client.jar"}},"#,
+            1,
+        );
+        let dir = versions_dir(&[("1.20.1", VANILLA), ("fabric-loader-0.15.11-1.20.1", &own_client)]);
+        let details = load_version(dir.path(), "fabric-loader-0.15.11-1.20.1").expect("resolves");
+        assert_eq!(details.jar, None);
+        assert_eq!(details.jar_id(), "fabric-loader-0.15.11-1.20.1");
+    }
+
+    #[test]
+    fn forge_profile_gets_a_copy_of_the_client_jar() {
+        let dir = versions_dir(&[("1.20.1", VANILLA), ("1.20.1-forge-47.2.0", FORGE)]);
+        fs::write(dir.path().join("1.20.1/1.20.1.jar"), "client").expect("client JAR");
+        let details = load_version(dir.path(), "1.20.1-forge-47.2.0").expect("resolves");
+
+        // Named after ${version_name}, as -DignoreList expects
+        let forge_jar = client_jar(dir.path(), &details).expect("copied");
+        assert_eq!(forge_jar, dir.path().join("1.20.1-forge-47.2.0/1.20.1-forge-47.2.0.jar"));
+        assert_eq!(fs::read_to_string(&forge_jar).expect("copy"), "client");
+
+        let vanilla = load_version(dir.path(), "1.20.1").expect("resolves");
+        assert_eq!(client_jar(dir.path(), &vanilla).expect("in place"), dir.path().join("1.20.1/1.20.1.jar"));
+    }
+
+    #[test]
+    fn inheritance_cycle() {
+        let a = r#"{"id": "a", "type": "release", "inheritsFrom": "b"}"#;
+        let b = r#"{"id": "b", "type": "release", "inheritsFrom": "a"}"#;
+        let dir = versions_dir(&[("a", a), ("b", b)]);
+        let error = load_version(dir.path(), "a").expect_err("cycle");
+        match error.downcast_ref::<LauncherError>() {
+            Some(LauncherError::InheritanceCycle(chain)) => assert_eq!(chain, &["a", "b", "a"]),
+            other => panic!("expected an inheritance cycle, got {:?}", other),
+        }
+    }
+}
@@ -6,6 +6,7 @@ use serde::{Deserialize, Serialize};
 use std::fs;
 use std::io::{stdout, Write};
 use std::path::PathBuf;
+use std::time::Duration;
 use uuid::Uuid;
 
 // Re-export YggdrasilProfile for use in main.rs
@@ -13,6 +14,8 @@ pub use crate::models::YggdrasilProfile;
 
 const AUTHLIB_INJECTOR_API_BASE: &str = "https://authlib-injector.yushi.moe";
 const AUTHLIB_INJECTOR_API_MIRROR: &str = "https://bmclapi2.bangbang93.com/mirrors/authlib-injector";
+/// How long the API root may take to answer before the server counts as unreachable
+const API_METADATA_TIMEOUT: Duration = Duration::from_secs(10);
 
 /// Returns the formatted UUID with dashes
 fn format_uuid(uuid: &str) -> String {
@@ -82,17 +85,16 @@ impl YggdrasilAuthenticator {
         let client = Client::new();
         let res = client.get(&url).send().await?;
 
-        if res.status().is_success() {
-            if let Some(ali_header) = res.headers().get("X-Authlib-Injector-API-Location") {
-                let ali_value = ali_header.to_str().unwrap_or("");
-                if !ali_value.is_empty() {
-                    // Make it absolute URL
-                    let resolved_url = url::Url::parse(&url)?;
-                    let absolute_url = resolved_url.join(ali_value)?;
-                    let absolute_str = absolute_url.to_string();
-                    if absolute_str != url {
-                        return Ok(absolute_str.trim_end_matches('/').to_string());
-                    }
+        if res.status().is_success()
+            && let Some(ali_header) = res.headers().get("X-Authlib-Injector-API-Location") {
+            let ali_value = ali_header.to_str().unwrap_or("");
+            if !ali_value.is_empty() {
+                // Make it absolute URL
+                let resolved_url = url::Url::parse(&url)?;
+                let absolute_url = resolved_url.join(ali_value)?;
+                let absolute_str = absolute_url.to_string();
+                if absolute_str != url {
+                    return Ok(absolute_str.trim_end_matches('/').to_string());
                 }
             }
         }
@@ -102,7 +104,7 @@ impl YggdrasilAuthenticator {
 
     pub async fn get_api_metadata(&self) -> anyhow::Result<YggdrasilApiMetadata> {
         let url = format!("{}/", self.api_url);
-        let res = self.client.get(&url).send().await?;
+        let res = self.client.get(&url).timeout(API_METADATA_TIMEOUT).send().await?;
 
         if !res.status().is_success() {
             let status = res.status();
@@ -246,6 +248,9 @@ pub struct YggdrasilAccount {
     pub user_id: String,
     /// User properties (JSON string)
     pub user_properties: String,
+    /// Base64 API metadata from the last launch, reused when offline
+    #[serde(default)]
+    pub prefetched_metadata: Option<String>,
 }
 
 impl YggdrasilAccount {
@@ -264,8 +269,7 @@ impl YggdrasilAccount {
             }
 
             // Use interactive selection
-            let profile = select_profile(&response.available_profiles)?;
-            profile
+            select_profile(&response.available_profiles)?
         };
 
         let user_id = response.user.as_ref().context("User info not available")?.id.clone();
@@ -283,6 +287,7 @@ impl YggdrasilAccount {
             client_token: response.client_token,
             user_id: format_uuid(&user_id),
             user_properties: user_properties_json,
+            prefetched_metadata: None,
         })
     }
 
@@ -318,6 +323,12 @@ impl AuthlibInjector {
         Self { cache_dir }
     }
 
+    /// The previously downloaded jar, if there is one.
+    pub fn cached(&self) -> Option<PathBuf> {
+        let jar_path = self.cache_dir.join("authlib-injector.jar");
+        jar_path.exists().then_some(jar_path)
+    }
+
     pub async fn get_or_download(&self) -> anyhow::Result<PathBuf> {
         let jar_path = self.cache_dir.join("authlib-injector.jar");
 
@@ -398,7 +409,7 @@ impl AuthlibInjector {
 
                 if total_bytes > 0 {
                     let progress = (downloaded as f64 / total_bytes as f64 * 100.0) as u32;
-                    if downloaded % (1024 * 1024) == 0 || downloaded == total_bytes {
+                    if downloaded.is_multiple_of(1024 * 1024) || downloaded == total_bytes {
                         println!(
                             "Downloaded: {}/{} bytes ({:.0}%)",
                             downloaded,